@@ -0,0 +1,117 @@
+use crate::error::Error;
+use crate::stack::Limits;
+use std::env;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+const USAGE: &str = "usage: dcrs [--rc PATH | --no-rc] [--session PATH] [--autosave]
+             [--on-error continue|line|abort] [--max-depth N] [--max-ops N]
+             [--max-stack N] [--dc-compat] [-f FILE]...
+             [--map PROGRAM [--lines] [--persist]]
+With --autosave, the saved session is loaded and then the rc file runs on top
+of it, so everything the rc file sets wins. Clear a register before setting it
+in the rc file, as in 'Ca 42 sa', so its values don't pile up.";
+
+/// Command line options.
+#[derive(Debug, Default)]
+pub struct Args {
+    /// An rc file to run instead of the default one.
+    pub rc: Option<PathBuf>,
+    /// Don't run any rc file.
+    pub no_rc: bool,
+    /// The session file used by `save`, `load` and `--autosave`.
+    pub session: Option<PathBuf>,
+    /// Load the session file on start and save it on quit.
+    pub autosave: bool,
+    /// Scripts to run instead of starting the REPL.
+    pub files: Vec<PathBuf>,
+    /// A program to run on each number read from stdin, printing the result.
+    pub map: Option<String>,
+    /// Run the `map` program on each line rather than each number.
+    pub lines: bool,
+    /// Keep the stack between inputs to the `map` program.
+    pub persist: bool,
+    /// What to do after an error, if not the default for the mode.
+    pub on_error: Option<OnError>,
+    pub max_depth: Option<usize>,
+    pub max_ops: Option<u64>,
+    pub max_stack: Option<usize>,
+    /// Give single-letter ops their GNU dc meanings.
+    pub dc_compat: bool,
+}
+
+/// What to do with the rest of the input after an op fails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnError {
+    /// Carry on with the next op.
+    Continue,
+    /// Skip the rest of the line.
+    Line,
+    /// Stop running and exit with a non-zero status.
+    Abort,
+}
+
+impl Args {
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Args, Error> {
+        let mut parsed = Args::default();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--rc" => parsed.rc = Some(value(&arg, args.next())?.into()),
+                "--no-rc" => parsed.no_rc = true,
+                "--session" => parsed.session = Some(value(&arg, args.next())?.into()),
+                "--autosave" => parsed.autosave = true,
+                "--map" => parsed.map = Some(value(&arg, args.next())?),
+                "--lines" => parsed.lines = true,
+                "--persist" => parsed.persist = true,
+                "--on-error" => {
+                    parsed.on_error = Some(match value(&arg, args.next())?.as_str() {
+                        "continue" => OnError::Continue,
+                        "line" => OnError::Line,
+                        "abort" => OnError::Abort,
+                        policy => {
+                            let msg = format!("unknown error policy '{}'\n{}", policy, USAGE);
+                            return Err(Error::Usage(msg));
+                        }
+                    })
+                }
+                "--max-depth" => {
+                    let depth = number(&arg, args.next())?;
+                    if depth > Limits::MAX_DEPTH {
+                        let msg =
+                            format!("{} can be at most {}\n{}", arg, Limits::MAX_DEPTH, USAGE);
+                        return Err(Error::Usage(msg));
+                    }
+                    parsed.max_depth = Some(depth);
+                }
+                "--max-ops" => parsed.max_ops = Some(number(&arg, args.next())?),
+                "--max-stack" => parsed.max_stack = Some(number(&arg, args.next())?),
+                "--dc-compat" => parsed.dc_compat = true,
+                "-f" | "--file" => parsed.files.push(value(&arg, args.next())?.into()),
+                _ => return Err(Error::Usage(format!("unknown option '{}'\n{}", arg, USAGE))),
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+fn value(arg: &str, value: Option<String>) -> Result<String, Error> {
+    value.ok_or_else(|| Error::Usage(format!("{} needs a value\n{}", arg, USAGE)))
+}
+
+fn number<T: FromStr>(arg: &str, value: Option<String>) -> Result<T, Error> {
+    let value = self::value(arg, value)?;
+    value.parse().map_err(|_| {
+        Error::Usage(format!(
+            "{} needs a number, not '{}'\n{}",
+            arg, value, USAGE
+        ))
+    })
+}
+
+/// `$XDG_CONFIG_HOME/dcrs/dcrsrc`, falling back to `~/.config`.
+pub fn default_rc() -> Option<PathBuf> {
+    let config = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(config.join("dcrs").join("dcrsrc"))
+}
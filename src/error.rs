@@ -6,11 +6,25 @@ use std::io;
 #[derive(Debug)]
 pub enum Error {
     InvalidInput(String),
-    EndOfStack,
+    EndOfStack(usize, usize),
     EndOfRegister(String),
     BadRadix,
     Io(io::Error),
     DivideByZero,
+    Domain(&'static str),
+    Overflow,
+    BadShift,
+    BadCount,
+    BadIndex,
+    Session(String),
+    Usage(String),
+    BadDefinition(String),
+    UnknownWord(String),
+    UndefinedWord(String),
+    EndOfInput,
+    DepthLimit(usize),
+    OpLimit(u64),
+    StackLimit(usize),
 }
 
 impl fmt::Display for Error {
@@ -18,11 +32,33 @@ impl fmt::Display for Error {
         use Error::*;
         match self {
             InvalidInput(s) => write!(f, "Invalid input: '{}'", s),
-            EndOfStack => write!(f, "Stack is empty"),
+            EndOfStack(needed, available) => write!(
+                f,
+                "Stack has {} item(s) but {} are needed",
+                available, needed
+            ),
             EndOfRegister(id) => write!(f, "Register stack '{}' is empty", id.trim()),
             Io(error) => write!(f, "IO Error: {}", error),
             BadRadix => write!(f, "Bad radix"),
             DivideByZero => write!(f, "Attempt to divide by zero"),
+            Domain(s) => write!(f, "Undefined: {}", s),
+            Overflow => write!(f, "Arithmetic overflow"),
+            BadShift => write!(f, "Shift amount out of range"),
+            BadCount => write!(f, "Bad count"),
+            BadIndex => write!(f, "Bad array index"),
+            Session(s) => write!(f, "Bad session file {}", s),
+            Usage(s) => write!(f, "{}", s),
+            BadDefinition(s) => write!(f, "Bad definition: {}", s),
+            UnknownWord(s) => write!(f, "Unknown word '{}'", s),
+            UndefinedWord(s) => write!(
+                f,
+                "'{}' is not a number, an op or a word in the dictionary",
+                s
+            ),
+            EndOfInput => write!(f, "End of input"),
+            DepthLimit(n) => write!(f, "Words nested more than {} deep", n),
+            OpLimit(n) => write!(f, "More than {} ops run by one token", n),
+            StackLimit(n) => write!(f, "Stack grew past {} items", n),
         }
     }
 }
@@ -34,3 +70,41 @@ impl From<io::Error> for Error {
         Error::Io(error)
     }
 }
+
+/// Where a token was in the input. Lines and columns count from 1.
+#[derive(Debug)]
+pub struct Span {
+    pub line: usize,
+    pub col: usize,
+    pub token: String,
+}
+
+/// An error from running a token, with the op that failed and the values it
+/// was given. The op is the token itself unless it failed inside a word or a
+/// line read by `?`.
+#[derive(Debug)]
+pub struct Diagnostic {
+    pub error: Error,
+    pub op: String,
+    pub operands: Vec<String>,
+    pub span: Option<Span>,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.error)?;
+        let token = self.span.as_ref().map(|span| span.token.as_str());
+        let nested = token.is_some_and(|token| token != self.op);
+        if self.operands.is_empty() && !nested {
+            return Ok(());
+        }
+        write!(f, " ('{}'", self.op)?;
+        if !self.operands.is_empty() {
+            write!(f, " on {}", self.operands.join(" "))?;
+        }
+        if let (true, Some(token)) = (nested, token) {
+            write!(f, " in '{}'", token)?;
+        }
+        write!(f, ")")
+    }
+}
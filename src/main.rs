@@ -1,26 +1,305 @@
+mod args;
 mod error;
 mod stack;
 
-use error::Error;
-use stack::Stack;
+use args::{Args, OnError};
+use error::{Diagnostic, Error, Span};
+use stack::{Limits, Program, Stack};
+use std::cell::Cell;
+use std::env;
+use std::fs;
 use std::io::{self, Write};
+use std::panic;
+use std::path::Path;
+use std::process;
+use std::thread;
 
 fn main() -> Result<(), Error> {
+    let args = Args::parse(env::args().skip(1)).unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        process::exit(2);
+    });
+    let defaults = Limits::default();
+    let limits = Limits {
+        depth: args.max_depth.unwrap_or(defaults.depth),
+        ops: args.max_ops.unwrap_or(defaults.ops),
+        stack: args.max_stack.unwrap_or(defaults.stack),
+    };
+    // Words call words recursively, so run on a thread with the stack the
+    // depth limit needs rather than whatever the main thread was given.
+    let interpreter = thread::Builder::new()
+        .stack_size(limits.stack_size())
+        .spawn(move || run(args, limits))
+        .unwrap_or_else(|error| {
+            eprintln!("Error: can't start the interpreter: {}", error);
+            process::exit(2);
+        });
+    match interpreter.join() {
+        Ok(result) => result,
+        Err(panic) => panic::resume_unwind(panic),
+    }
+}
+
+fn run(args: Args, limits: Limits) -> Result<(), Error> {
     let mut stack = Stack::new();
+    stack.set_pretty(args.map.is_none() && termion::is_tty(&io::stdout()));
+    stack.set_limits(limits);
+    if let Some(path) = args.session {
+        stack.set_session(path);
+    }
+    // The session is loaded before the rc file, and the rc file runs on top
+    // of it, so everything the rc file sets wins over the saved session.
+    if args.autosave {
+        if let Some(path) = stack.session().filter(|path| path.exists()).cloned() {
+            if let Err(error) = stack.load(&path) {
+                eprintln!("Error: {}", error);
+            }
+        }
+    }
+    // A session saved in dc's dialect stays in it, and --dc-compat keeps the
+    // input radix a session saved.
+    if args.dc_compat {
+        stack.set_dc_compat(true);
+    }
+    let rc = match args.rc {
+        _ if args.no_rc => None,
+        Some(path) => Some(path),
+        None => args::default_rc().filter(|path| path.exists()),
+    };
+    if let Some(path) = rc {
+        run_file(&mut stack, &path, OnError::Continue);
+    }
+    // Scripts stop at the first error, like dc in CI; interactive use and
+    // filters carry on. The rc file never stops startup.
+    let on_error = |default| args.on_error.unwrap_or(default);
+    let mut flow = Flow::Next;
+    for path in args.files.iter() {
+        flow = run_file(&mut stack, path, on_error(OnError::Abort));
+        if flow != Flow::Next {
+            break;
+        }
+    }
+    if flow == Flow::Next {
+        flow = match args.map {
+            Some(program) => map(
+                &mut stack,
+                &program,
+                args.lines,
+                args.persist,
+                on_error(OnError::Continue),
+            )?,
+            None if args.files.is_empty() => repl(&mut stack, on_error(OnError::Continue))?,
+            None => Flow::Next,
+        };
+    }
+    if flow == Flow::Abort {
+        process::exit(1);
+    }
+    if args.autosave {
+        if let Some(path) = stack.session().cloned() {
+            stack.save(&path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Runs each line of an rc file or script, reporting errors with their line
+/// number.
+fn run_file(stack: &mut Stack, path: &Path, on_error: OnError) -> Flow {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(error) => {
+            eprintln!("{}: Error: {}", path.display(), error);
+            return match on_error {
+                OnError::Abort => Flow::Abort,
+                _ => Flow::Next,
+            };
+        }
+    };
+    // The line the definition still open, if any, was started on.
+    let mut start = 0;
+    for (n, line) in text.lines().enumerate() {
+        if !stack.defining() {
+            start = n + 1;
+        }
+        let flow = exec(stack, &Program::parse(line), n + 1, on_error, |d| {
+            located(&path.display(), d)
+        });
+        if flow != Flow::Next {
+            return flow;
+        }
+    }
+    // A definition can't run on past the file, into the next file or the REPL.
+    if let Err(error) = stack.end_definition() {
+        eprintln!("{}:{}: Error: {}", path.display(), start, error);
+        if on_error == OnError::Abort {
+            return Flow::Abort;
+        }
+    }
+    Flow::Next
+}
+
+const PROMPT: &str = "► ";
+
+fn repl(stack: &mut Stack, on_error: OnError) -> Result<Flow, Error> {
     loop {
-        print!("► ");
+        print!("{}", PROMPT);
         io::stdout().flush()?;
         let mut line = String::new();
-        io::stdin().read_line(&mut line)?;
-        for op in line.split_whitespace() {
-            match op.parse().and_then(|op| stack.op(op)) {
-                Ok(quit) => {
-                    if quit {
-                        return Ok(());
-                    }
+        if stack.read_line(&mut line)? == 0 {
+            return Ok(Flow::Quit);
+        }
+        let flow = exec(stack, &Program::parse(&line), 1, on_error, |d| {
+            caret(&line, d)
+        });
+        if flow != Flow::Next {
+            return Ok(flow);
+        }
+    }
+}
+
+/// Compiles `program` and runs it on each number, or each line of numbers,
+/// read from stdin and prints the top of the stack after each run. Input is
+/// only ever pushed as numbers, never run. Inputs that fail aren't printed,
+/// and the program isn't run on input that isn't a number, or on blank lines.
+/// Errors give the input line, and the column in the program if it failed.
+fn map(
+    stack: &mut Stack,
+    program: &str,
+    lines: bool,
+    persist: bool,
+    on_error: OnError,
+) -> Result<Flow, Error> {
+    let program = Program::compile(program, stack.dialect());
+    let mut line = String::new();
+    let mut n = 0;
+    loop {
+        line.clear();
+        n += 1;
+        if stack.read_line(&mut line)? == 0 {
+            return Ok(Flow::Quit);
+        }
+        let input = Program::parse(&line);
+        let tokens: Vec<_> = input.iter().collect();
+        if tokens.is_empty() {
+            continue;
+        }
+        let inputs: Vec<&[_]> = if lines {
+            vec![&tokens]
+        } else {
+            tokens.chunks(1).collect()
+        };
+        for numbers in inputs {
+            if !persist {
+                stack.clear();
+            }
+            let mut numeric = true;
+            for number in numbers {
+                if let Err(mut d) = stack.push_number(&number.token) {
+                    d.span = Some(Span {
+                        line: n,
+                        col: number.col,
+                        token: number.token.clone(),
+                    });
+                    located(&"<stdin>", d);
+                    numeric = false;
+                }
+            }
+            if !numeric {
+                match on_error {
+                    OnError::Abort => return Ok(Flow::Abort),
+                    _ => continue,
+                }
+            }
+            let failed = Cell::new(false);
+            let flow = exec(stack, &program, 1, on_error, |d| {
+                let col = d.span.as_ref().map_or(1, |span| span.col);
+                eprintln!("<stdin>:{}: Error: {} in --map:{}", n, d, col);
+                failed.set(true);
+            });
+            if flow != Flow::Next {
+                return Ok(flow);
+            }
+            if failed.get() {
+                continue;
+            }
+            match stack.top() {
+                Some(_) => stack.print_top(),
+                None => eprintln!(
+                    "<stdin>:{}: Error: {} after --map",
+                    n,
+                    Error::EndOfStack(1, 0)
+                ),
+            }
+        }
+    }
+}
+
+/// Whether to keep running after a line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Flow {
+    Next,
+    /// An op asked to quit, or the input ended.
+    Quit,
+    /// An op failed and the error policy is to abort.
+    Abort,
+}
+
+/// Runs each op in `program`, reporting errors and then carrying on, skipping
+/// the rest of the line or aborting as `on_error` says.
+fn exec(
+    stack: &mut Stack,
+    program: &Program,
+    line_no: usize,
+    on_error: OnError,
+    report: impl Fn(Diagnostic),
+) -> Flow {
+    for instr in program.iter() {
+        match stack.eval_instr(instr) {
+            Ok(quit) => {
+                if quit {
+                    return Flow::Quit;
+                }
+            }
+            Err(mut d) => {
+                d.span = Some(Span {
+                    line: line_no,
+                    col: instr.col,
+                    token: instr.token.clone(),
+                });
+                report(d);
+                match on_error {
+                    OnError::Continue => (),
+                    OnError::Line => return Flow::Next,
+                    OnError::Abort => return Flow::Abort,
                 }
-                Err(error) => eprintln!("Error: {}", error),
             }
         }
     }
+    Flow::Next
+}
+
+/// Reports an error as `name:line:col: Error: ...`, for files and --map input.
+fn located(name: &dyn std::fmt::Display, d: Diagnostic) {
+    match &d.span {
+        Some(span) => eprintln!("{}:{}:{}: Error: {}", name, span.line, span.col, d),
+        None => eprintln!("{}: Error: {}", name, d),
+    }
+}
+
+/// Reports an error in the REPL with a caret under the token that failed.
+/// Tabs before the token are copied, so the caret lines up however wide the
+/// terminal makes them.
+fn caret(line: &str, d: Diagnostic) {
+    if let Some(span) = &d.span {
+        let before = line.chars().take(span.col - 1);
+        let indent: String = PROMPT
+            .chars()
+            .chain(before)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let width = span.token.chars().count();
+        eprintln!("{}{}", indent, "^".repeat(width));
+    }
+    eprintln!("Error: {}", d);
 }
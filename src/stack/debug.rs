@@ -0,0 +1,164 @@
+use crate::error::Error;
+use crate::stack::op::{DebugOp, Op, PrintOp, RegOp};
+use crate::stack::Stack;
+use std::collections::BTreeSet;
+
+const HELP: &str = "\
+s, step      run the next op, stopping inside words
+n, next      run the next op, stepping over words
+c, continue  run until the next breakpoint
+where        show the words being run
+f, regs, Fa  print the stack or registers, as the ops do";
+
+/// Where the debugger should next stop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stepping {
+    Off,
+    /// Before the next op.
+    Step,
+    /// Before the next op no more than this many words deep.
+    Next(usize),
+}
+
+/// Trace and breakpoint settings. Ops only go through the debugger when
+/// tracing or stepping, so it costs nothing otherwise.
+pub struct Debugger {
+    trace: bool,
+    breakpoints: BTreeSet<String>,
+    stepping: Stepping,
+}
+
+impl Debugger {
+    pub fn new() -> Self {
+        Debugger {
+            trace: false,
+            breakpoints: BTreeSet::new(),
+            stepping: Stepping::Off,
+        }
+    }
+
+    pub fn active(&self) -> bool {
+        self.trace || self.stepping != Stepping::Off
+    }
+
+    fn stops_at(&self, depth: usize) -> bool {
+        match self.stepping {
+            Stepping::Off => false,
+            Stepping::Step => true,
+            Stepping::Next(level) => depth <= level,
+        }
+    }
+}
+
+impl Stack {
+    pub(super) fn debug_op(&mut self, op: DebugOp) {
+        use DebugOp::*;
+        match op {
+            Trace(on) => self.debug.trace = on,
+            Break(name) => {
+                self.debug.breakpoints.insert(name);
+            }
+            Unbreak(name) => {
+                self.debug.breakpoints.remove(&name);
+            }
+            Breakpoints => {
+                for name in self.debug.breakpoints.iter() {
+                    println!("{}", name);
+                }
+            }
+            Step => self.debug.stepping = Stepping::Step,
+        }
+    }
+
+    /// Called as the word `name` starts, to stop at its first op if it has a
+    /// breakpoint.
+    pub(super) fn debug_enter(&mut self, name: &str) {
+        if self.debug.breakpoints.contains(name) {
+            eprintln!("Breakpoint in '{}'", name);
+            self.debug.stepping = Stepping::Step;
+        }
+    }
+
+    /// Runs `op`, stopping before it if stepping and tracing it if asked.
+    pub(super) fn debug_run(&mut self, token: &str, op: Op) -> Result<bool, Error> {
+        if self.debug.stops_at(self.calls.len()) {
+            self.debug_prompt(token)?;
+        }
+        if !self.debug.trace {
+            return self.op(op);
+        }
+        let before = self.stack_line();
+        let result = self.op(op);
+        let indent = 2 * self.calls.len();
+        match &result {
+            Ok(_) => eprintln!(
+                "{:indent$}{} [{}] -> [{}]",
+                "",
+                token,
+                before,
+                self.stack_line(),
+                indent = indent
+            ),
+            Err(error) => eprintln!(
+                "{:indent$}{} [{}] -> Error: {}",
+                "",
+                token,
+                before,
+                error,
+                indent = indent
+            ),
+        }
+        result
+    }
+
+    /// Reads debugger commands until one says to run on. The end of input
+    /// runs on to the next breakpoint.
+    fn debug_prompt(&mut self, token: &str) -> Result<(), Error> {
+        match self.calls.last() {
+            Some(word) => eprintln!("At '{}' in '{}': [{}]", token, word, self.stack_line()),
+            None => eprintln!("At '{}': [{}]", token, self.stack_line()),
+        }
+        loop {
+            eprint!("(debug) ");
+            let mut line = String::new();
+            if self.read_line(&mut line)? == 0 {
+                self.debug.stepping = Stepping::Off;
+                return Ok(());
+            }
+            match line.trim() {
+                "" | "s" | "step" => self.debug.stepping = Stepping::Step,
+                "n" | "next" => self.debug.stepping = Stepping::Next(self.calls.len()),
+                "c" | "continue" => self.debug.stepping = Stepping::Off,
+                "where" => {
+                    for (depth, word) in self.calls.iter().enumerate().rev() {
+                        eprintln!("{:>3} {}", depth + 1, word);
+                    }
+                    continue;
+                }
+                "help" => {
+                    eprintln!("{}", HELP);
+                    continue;
+                }
+                command => {
+                    match command.parse() {
+                        Ok(op @ Op::Print(PrintOp::Dump | PrintOp::Registers))
+                        | Ok(op @ Op::Reg(RegOp::Dump, Some(_))) => {
+                            if let Err(error) = self.op(op) {
+                                eprintln!("Error: {}", error);
+                            }
+                        }
+                        _ => eprintln!("Unknown debugger command '{}', try 'help'", command),
+                    }
+                    continue;
+                }
+            }
+            return Ok(());
+        }
+    }
+
+    /// The stack, bottom first, for traces.
+    fn stack_line(&self) -> String {
+        let vals: Vec<String> = self.stack.iter().map(|val| self.plain(val)).collect();
+        vals.join(" ")
+    }
+}
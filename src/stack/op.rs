@@ -1,66 +1,204 @@
 use crate::error::Error;
-use crate::stack::val::Val;
+use crate::stack::registers::RegId;
+use crate::stack::val::{Mode, Val};
 use std::convert::From;
+use std::path::PathBuf;
 use std::str::FromStr;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Op {
     Stack(StackOp),
-    Reg(RegOp),
+    /// A register operation on the named register, or on the register whose
+    /// id is popped from the stack if there is no name.
+    Reg(RegOp, Option<RegId>),
     Print(PrintOp),
     Calc(CalcOp),
     Cast(CastOp),
+    Mode(Mode),
+    ClearRegs,
+    Session(SessionOp),
+    /// Runs a user-defined word.
+    Word(String),
+    Forget(String),
+    Input(InputOp),
+    /// Pops the radix numbers are read in, as dc's `i` does.
+    InputRadix,
+    Debug(DebugOp),
     Quit,
+    /// Returns from the number of words popped from the stack, like dc's `Q`.
+    /// It never quits the program.
+    QuitWords,
 }
 
 impl FromStr for Op {
     type Err = Error;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(match s {
-            // Calculator operations
-            "+" => Op::Calc(CalcOp::Add),
-            "-" => Op::Calc(CalcOp::Sub),
-            "*" => Op::Calc(CalcOp::Mul),
-            "/" => Op::Calc(CalcOp::Div),
-            "%" => Op::Calc(CalcOp::Mod),
-            "^" => Op::Calc(CalcOp::Pow),
-            "&" => Op::Calc(CalcOp::And),
-            "|" => Op::Calc(CalcOp::Or),
-            "!" => Op::Calc(CalcOp::Not),
-            "x" => Op::Calc(CalcOp::Xor),
-            "<" => Op::Calc(CalcOp::Shl),
-            ">" => Op::Calc(CalcOp::Shr),
-
-            // Printing operations
-            "p" => Op::Print(PrintOp::Print),
-            "f" => Op::Print(PrintOp::Dump),
-            "o" => Op::Print(PrintOp::Output),
-
-            // Stack operations
-            "n" => Op::Stack(StackOp::Pop),
-            "d" => Op::Stack(StackOp::Dup),
-            "c" => Op::Stack(StackOp::Clear),
-            "r" => Op::Stack(StackOp::Rev),
-
-            // Register operations
-            "s" => Op::Reg(RegOp::Push),
-            "l" => Op::Reg(RegOp::Get),
-            "L" => Op::Reg(RegOp::Pop),
-            "F" => Op::Reg(RegOp::Dump),
-
-            // Casting operations
-            "u" => Op::Cast(CastOp::U),
-            "i" => Op::Cast(CastOp::I),
-
-            "q" => Op::Quit,
-
-            // If none of the above, parse this as a Val and push to the stack.
-            _ => Op::Stack(StackOp::Push(s.parse()?)),
+        Op::parse(s, Dialect::Dcrs)
+    }
+}
+
+/// How tokens are read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dialect {
+    Dcrs,
+    /// The ops in `DC_OPS` take the place of the ones they share a token
+    /// with, and numbers are read in the input radix, with `_` for negative.
+    Dc(Radix),
+}
+
+impl Dialect {
+    /// Whether `token` reads differently as the input radix changes, so it
+    /// can't be parsed ahead of time.
+    pub fn reads_radix(&self, token: &str) -> bool {
+        matches!(self, Dialect::Dc(_))
+            && lookup(DC_OPS, token).is_none()
+            && dc_digits(token).is_some()
+    }
+
+    /// Reads `token` as a number, as `Op::parse` would in this dialect, for
+    /// input that must not run as code.
+    pub fn number(&self, token: &str) -> Result<Val, Error> {
+        match self {
+            Dialect::Dc(input) => dc_number(token, *input).unwrap_or_else(|| token.parse()),
+            Dialect::Dcrs => token.parse(),
+        }
+    }
+}
+
+impl Op {
+    /// Parses a token using the `OPS` table, and `DC_OPS` first for dc.
+    /// Anything that isn't in a table is a number if it parses as one, or
+    /// else a user-defined word. Tokens written as numbers that don't fit
+    /// are invalid rather than words.
+    pub fn parse(s: &str, dialect: Dialect) -> Result<Self, Error> {
+        if let Dialect::Dc(input) = dialect {
+            if let Some(op) = lookup(DC_OPS, s) {
+                return Ok(op);
+            }
+            if let Some(val) = dc_number(s, input) {
+                return val.map(|val| Op::Stack(StackOp::Push(val)));
+            }
+        }
+        if let Some(op) = lookup(OPS, s) {
+            return Ok(op);
+        }
+        match s.parse() {
+            Ok(val) => Ok(Op::Stack(StackOp::Push(val))),
+            Err(error) if numeric(s) => Err(error),
+            Err(_) => Ok(Op::Word(s.into())),
+        }
+    }
+
+    /// The heading `help` lists the op under.
+    pub fn group(&self) -> &'static str {
+        match self {
+            Op::Calc(_) => "Calc",
+            Op::Stack(_) => "Stack",
+            Op::Reg(..) | Op::ClearRegs => "Reg",
+            Op::Print(_) => "Print",
+            Op::Cast(_) => "Cast",
+            Op::Mode(_) => "Mode",
+            Op::Session(_) => "Session",
+            Op::Word(_) | Op::Forget(_) => "Words",
+            Op::Input(_) | Op::InputRadix => "Input",
+            Op::Debug(_) => "Debug",
+            Op::Quit | Op::QuitWords => "Control",
+        }
+    }
+}
+
+/// Finds the op for `s` in `table`. Register ops are tried first, then ops
+/// taking an argument, then exact tokens.
+fn lookup(table: &[OpInfo], s: &str) -> Option<Op> {
+    let reg = table.iter().find_map(|info| match &info.syntax {
+        Syntax::Reg(op) => reg_op(s, info.token, op),
+        _ => None,
+    });
+    let arg = || {
+        table.iter().find_map(|info| match &info.syntax {
+            Syntax::Arg(f) => s.strip_prefix(info.prefix()).map(f),
+            _ => None,
+        })
+    };
+    let exact = || {
+        table.iter().find_map(|info| match &info.syntax {
+            Syntax::Exact(op) if info.token == s => Some(op.clone()),
+            _ => None,
+        })
+    };
+    reg.or_else(arg).or_else(exact)
+}
+
+/// The entry `help` describes for `s`: the op's bare token or usage, like
+/// `s` or `s<reg>`, or the op written as it would be run, like `sa`.
+pub fn info<'a>(table: &'a [OpInfo], s: &str) -> Option<&'a OpInfo> {
+    let named = table
+        .iter()
+        .find(|info| info.token == s || info.usage() == s);
+    named.or_else(|| {
+        table.iter().find(|info| match &info.syntax {
+            Syntax::Reg(op) => reg_op(s, info.token, op).is_some(),
+            Syntax::Arg(_) => s.starts_with(info.prefix()),
+            Syntax::Exact(_) => false,
         })
+    })
+}
+
+/// Reads a dc number: digits in the input radix, with upper case letters for
+/// hex as in dc, and `_` in front for negative. Returns None if `s` isn't
+/// written like a number, and an error if its digits don't fit the radix.
+fn dc_number(s: &str, input: Radix) -> Option<Result<Val, Error>> {
+    let (sign, digits) = dc_digits(s)?;
+    let prefix = match input {
+        Radix::Bin => "0b",
+        Radix::Dec => "",
+        Radix::Hex => "0x",
+    };
+    let val = format!("{}{}{}", sign, prefix, digits).parse();
+    Some(val.map_err(|_| Error::InvalidInput(s.into())))
+}
+
+/// Whether `s` is written as a number, with an optional `-` and `0x` or `0b`
+/// prefix, whether or not it fits in 64 bits.
+fn numeric(s: &str) -> bool {
+    let s = s.strip_prefix('-').unwrap_or(s);
+    let (digits, radix) = match (s.strip_prefix("0x"), s.strip_prefix("0b")) {
+        (Some(digits), _) => (digits, 16),
+        (_, Some(digits)) => (digits, 2),
+        _ => (s, 10),
+    };
+    !digits.is_empty() && digits.chars().all(|c| c.is_digit(radix))
+}
+
+/// Splits a dc number into its sign and digits.
+fn dc_digits(s: &str) -> Option<(&'static str, &str)> {
+    let (sign, digits) = match s.strip_prefix('_') {
+        Some(digits) => ("-", digits),
+        None => ("", s),
+    };
+    let digit = |c| matches!(c, '0'..='9' | 'A'..='F');
+    (!digits.is_empty() && digits.chars().all(digit)).then_some((sign, digits))
+}
+
+/// Parses register operations: `s` takes the register id from the stack,
+/// `sa` names register `a`, and `s:name` names a register with a longer name.
+/// The array operations `:a` and `;a` always need a name.
+fn reg_op(s: &str, token: &str, op: &RegOp) -> Option<Op> {
+    let name = s.strip_prefix(token)?;
+    let id = match name.strip_prefix(':') {
+        Some(name) if !name.is_empty() => Some(RegId::Name(name.into())),
+        Some(_) => return None,
+        None if name.is_empty() => None,
+        None if name.chars().count() == 1 => Some(RegId::Name(name.into())),
+        None => return None,
+    };
+    match (op, &id) {
+        (RegOp::Store | RegOp::Load, None) => None,
+        _ => Some(Op::Reg(op.clone(), id)),
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum CalcOp {
     Add,
     Sub,
@@ -74,39 +212,134 @@ pub enum CalcOp {
     Not,
     Shl,
     Shr,
+    Lsr,
+    Asr,
+    RotL,
+    RotR,
+    PopCnt,
+    Clz,
+    Ctz,
+    Sqrt,
+    Root,
+    Log2,
+    CLog2,
+    Log10,
+    CLog10,
+    NextPow2,
+    IsPow2,
+    Neg,
+    Abs,
+    Sgn,
+    Min,
+    Max,
+    Cmp,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum RegOp {
     Push,
     Get,
     Pop,
     Dump,
+    Store,
+    Load,
+    Clear,
+    /// Replaces the top of the register, as dc's `s` does.
+    Replace,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum StackOp {
     Push(Val),
     Pop,
+    Drop,
     Dup,
     Clear,
     Rev,
+    Rotate,
+    Depth,
+    Over,
+    Rot,
+    RotBack,
+    Pick,
+    Roll,
+    Nip,
+    Tuck,
+    DropN,
+    DupN,
+    Sort,
+    RSort,
+    Unique,
+    Reduce(Reduce),
+    ReduceN(Reduce),
+}
+
+/// Folds over the whole stack, or the top N items.
+#[derive(Debug, Clone, Copy)]
+pub enum Reduce {
+    Sum,
+    Product,
+    Min,
+    Max,
+    Mean,
+    Xor,
+    Or,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum PrintOp {
     Dump,
     Print,
+    /// Pops and prints without a newline, as dc's `n` does.
+    Write,
+    /// Prints the stack top first, as dc's `f` does.
+    DumpTopFirst,
     Output,
+    /// Sets the output radix without taking it from the stack.
+    Radix(Radix),
+    Registers,
+    Words,
+    /// Lists every op, or describes the one given.
+    Help(Option<String>),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum CastOp {
     U,
     I,
 }
 
-#[derive(Debug)]
+/// Reads the next line of input, which is stdin even when the program
+/// itself comes from a file.
+#[derive(Debug, Clone)]
+pub enum InputOp {
+    /// Runs the line.
+    Line,
+    /// Pushes every number on the line, skipping anything else.
+    Numbers,
+}
+
+/// Saves or loads the session file, at the given path or the default one.
+#[derive(Debug, Clone)]
+pub enum SessionOp {
+    Save(Option<PathBuf>),
+    Load(Option<PathBuf>),
+}
+
+#[derive(Debug, Clone)]
+pub enum DebugOp {
+    /// Prints each op with the stack before and after it.
+    Trace(bool),
+    /// Stops in the debugger when the named word runs.
+    Break(String),
+    Unbreak(String),
+    /// Lists the breakpoints.
+    Breakpoints,
+    /// Stops in the debugger before the next op.
+    Step,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Radix {
     Bin,
     Dec,
@@ -123,3 +356,927 @@ impl From<Val> for Option<Radix> {
         }
     }
 }
+
+/// How a token in `OPS` is written.
+pub enum Syntax {
+    /// Just the token.
+    Exact(Op),
+    /// The token followed by a register name: `sa`, `s:name`, or nothing to
+    /// pop a register number from the stack.
+    Reg(RegOp),
+    /// The token up to its `:`, followed by an argument: `save:PATH`.
+    Arg(fn(&str) -> Op),
+}
+
+/// One entry in the table of ops, which drives both parsing and `help`.
+pub struct OpInfo {
+    pub token: &'static str,
+    pub syntax: Syntax,
+    /// What the op takes from and leaves on the stack, Forth style.
+    pub effect: &'static str,
+    pub about: &'static str,
+    /// A program using the op, and the stack it leaves, bottom first. The
+    /// tests run every example.
+    pub example: (&'static str, &'static str),
+}
+
+impl OpInfo {
+    /// The token as `help` shows it.
+    pub fn usage(&self) -> String {
+        match self.syntax {
+            Syntax::Reg(_) => format!("{}<reg>", self.token),
+            _ => self.token.into(),
+        }
+    }
+
+    /// How many items the op takes, or `n` if that depends on a count. A
+    /// register op that can be written without a name, popping the register
+    /// number too, takes one more that way, as in `1/2`.
+    pub fn arity(&self) -> String {
+        let (takes, _) = self.effect.split_once("--").unwrap_or_default();
+        if takes.contains("...") {
+            return "n".into();
+        }
+        let takes = takes.split_whitespace().count();
+        match &self.syntax {
+            Syntax::Reg(op) if reg_op(self.token, self.token, op).is_some() => {
+                format!("{}/{}", takes, takes + 1)
+            }
+            _ => takes.to_string(),
+        }
+    }
+
+    pub fn group(&self) -> &'static str {
+        match &self.syntax {
+            Syntax::Exact(op) => op.group(),
+            Syntax::Reg(_) => "Reg",
+            Syntax::Arg(f) => f("").group(),
+        }
+    }
+
+    /// Describes the op on one line, with its example if it has one.
+    pub fn describe(&self) -> String {
+        let mut line = format!(
+            "{:<12} {:>3}  {:<24} {}",
+            self.usage(),
+            self.arity(),
+            self.effect,
+            self.about
+        );
+        match self.example {
+            ("", _) => (),
+            (program, "") => line += &format!(", e.g. {}", program),
+            (program, stack) => line += &format!(", e.g. {} leaves {}", program, stack),
+        }
+        line
+    }
+
+    fn prefix(&self) -> &'static str {
+        match self.token.find(':') {
+            Some(i) => &self.token[..=i],
+            None => self.token,
+        }
+    }
+}
+
+const fn op(
+    token: &'static str,
+    op: Op,
+    effect: &'static str,
+    about: &'static str,
+    example: (&'static str, &'static str),
+) -> OpInfo {
+    OpInfo {
+        token,
+        syntax: Syntax::Exact(op),
+        effect,
+        about,
+        example,
+    }
+}
+
+const fn reg(
+    token: &'static str,
+    op: RegOp,
+    effect: &'static str,
+    about: &'static str,
+    example: (&'static str, &'static str),
+) -> OpInfo {
+    OpInfo {
+        token,
+        syntax: Syntax::Reg(op),
+        effect,
+        about,
+        example,
+    }
+}
+
+const fn arg(
+    token: &'static str,
+    f: fn(&str) -> Op,
+    effect: &'static str,
+    about: &'static str,
+    example: (&'static str, &'static str),
+) -> OpInfo {
+    OpInfo {
+        token,
+        syntax: Syntax::Arg(f),
+        effect,
+        about,
+        example,
+    }
+}
+
+const NONE: (&str, &str) = ("", "");
+
+/// Every op, grouped as in `Op`.
+pub static OPS: &[OpInfo] = &[
+    // Calculator operations
+    op(
+        "+",
+        Op::Calc(CalcOp::Add),
+        "a b -- a+b",
+        "Add",
+        ("2 3 +", "5"),
+    ),
+    op(
+        "-",
+        Op::Calc(CalcOp::Sub),
+        "a b -- a-b",
+        "Subtract",
+        ("5 3 -", "2"),
+    ),
+    op(
+        "*",
+        Op::Calc(CalcOp::Mul),
+        "a b -- a*b",
+        "Multiply",
+        ("6 7 *", "42"),
+    ),
+    op(
+        "/",
+        Op::Calc(CalcOp::Div),
+        "a b -- a/b",
+        "Divide, rounding towards zero",
+        ("-7 2 /", "-3"),
+    ),
+    op(
+        "%",
+        Op::Calc(CalcOp::Mod),
+        "a b -- a%b",
+        "Remainder, with the sign of a",
+        ("-7 2 %", "-1"),
+    ),
+    op(
+        "^",
+        Op::Calc(CalcOp::Pow),
+        "a b -- a^b",
+        "Power; negative powers round towards zero",
+        ("2 10 ^", "1024"),
+    ),
+    op(
+        "&",
+        Op::Calc(CalcOp::And),
+        "a b -- a&b",
+        "Bitwise and",
+        ("12 10 &", "8"),
+    ),
+    op(
+        "|",
+        Op::Calc(CalcOp::Or),
+        "a b -- a|b",
+        "Bitwise or",
+        ("12 10 |", "14"),
+    ),
+    op(
+        "!",
+        Op::Calc(CalcOp::Not),
+        "a -- ~a",
+        "Bitwise not",
+        ("0 !", "18446744073709551615"),
+    ),
+    op(
+        "x",
+        Op::Calc(CalcOp::Xor),
+        "a b -- a^b",
+        "Bitwise xor",
+        ("12 10 x", "6"),
+    ),
+    op(
+        "xor",
+        Op::Calc(CalcOp::Xor),
+        "a b -- a^b",
+        "Bitwise xor, as x",
+        ("12 10 xor", "6"),
+    ),
+    op(
+        "<",
+        Op::Calc(CalcOp::Shl),
+        "a b -- a<<b",
+        "Shift left",
+        ("1 4 <", "16"),
+    ),
+    op(
+        ">",
+        Op::Calc(CalcOp::Shr),
+        "a b -- a>>b",
+        "Shift right, keeping the sign of signed values",
+        ("-16 2 >", "-4"),
+    ),
+    op(
+        "lsr",
+        Op::Calc(CalcOp::Lsr),
+        "a b -- a>>b",
+        "Shift right, filling with zeros",
+        ("-1 60 lsr", "15"),
+    ),
+    op(
+        "asr",
+        Op::Calc(CalcOp::Asr),
+        "a b -- a>>b",
+        "Shift right, filling with the sign bit",
+        ("0x8000000000000000 63 asr", "18446744073709551615"),
+    ),
+    op(
+        "rotl",
+        Op::Calc(CalcOp::RotL),
+        "a b -- a",
+        "Rotate left by b modulo 64",
+        ("0x8000000000000001 4 rotl", "24"),
+    ),
+    op(
+        "rotr",
+        Op::Calc(CalcOp::RotR),
+        "a b -- a",
+        "Rotate right by b modulo 64",
+        ("24 4 rotr", "9223372036854775809"),
+    ),
+    op(
+        "popcnt",
+        Op::Calc(CalcOp::PopCnt),
+        "a -- n",
+        "Count the set bits",
+        ("255 popcnt", "8"),
+    ),
+    op(
+        "clz",
+        Op::Calc(CalcOp::Clz),
+        "a -- n",
+        "Count the leading zero bits",
+        ("1 clz", "63"),
+    ),
+    op(
+        "ctz",
+        Op::Calc(CalcOp::Ctz),
+        "a -- n",
+        "Count the trailing zero bits",
+        ("256 ctz", "8"),
+    ),
+    op(
+        "v",
+        Op::Calc(CalcOp::Sqrt),
+        "a -- r",
+        "Square root, rounded down",
+        ("17 v", "4"),
+    ),
+    op(
+        "root",
+        Op::Calc(CalcOp::Root),
+        "a n -- r",
+        "nth root, rounded towards zero",
+        ("-27 3 root", "-3"),
+    ),
+    op(
+        "log2",
+        Op::Calc(CalcOp::Log2),
+        "a -- n",
+        "Base 2 logarithm, rounded down",
+        ("1000 log2", "9"),
+    ),
+    op(
+        "clog2",
+        Op::Calc(CalcOp::CLog2),
+        "a -- n",
+        "Base 2 logarithm, rounded up",
+        ("1000 clog2", "10"),
+    ),
+    op(
+        "log10",
+        Op::Calc(CalcOp::Log10),
+        "a -- n",
+        "Base 10 logarithm, rounded down",
+        ("999 log10", "2"),
+    ),
+    op(
+        "clog10",
+        Op::Calc(CalcOp::CLog10),
+        "a -- n",
+        "Base 10 logarithm, rounded up",
+        ("999 clog10", "3"),
+    ),
+    op(
+        "npow2",
+        Op::Calc(CalcOp::NextPow2),
+        "a -- p",
+        "Smallest power of two at least a",
+        ("100 npow2", "128"),
+    ),
+    op(
+        "ispow2",
+        Op::Calc(CalcOp::IsPow2),
+        "a -- flag",
+        "1 if a is a power of two, else 0",
+        ("64 ispow2", "1"),
+    ),
+    op(
+        "neg",
+        Op::Calc(CalcOp::Neg),
+        "a -- -a",
+        "Negate",
+        ("5 neg", "-5"),
+    ),
+    op(
+        "abs",
+        Op::Calc(CalcOp::Abs),
+        "a -- |a|",
+        "Absolute value",
+        ("-5 abs", "5"),
+    ),
+    op(
+        "sgn",
+        Op::Calc(CalcOp::Sgn),
+        "a -- s",
+        "Sign: -1, 0 or 1",
+        ("-5 sgn", "-1"),
+    ),
+    op(
+        "min",
+        Op::Calc(CalcOp::Min),
+        "a b -- m",
+        "Smaller of a and b",
+        ("3 -2 min", "-2"),
+    ),
+    op(
+        "max",
+        Op::Calc(CalcOp::Max),
+        "a b -- m",
+        "Larger of a and b",
+        ("3 -2 max", "3"),
+    ),
+    op(
+        "cmp",
+        Op::Calc(CalcOp::Cmp),
+        "a b -- c",
+        "Compare: -1, 0 or 1 as a is less, equal or greater",
+        ("2 3 cmp", "-1"),
+    ),
+    // Stack operations
+    op(
+        "n",
+        Op::Stack(StackOp::Pop),
+        "a --",
+        "Pop and print",
+        ("1 2 n", "1"),
+    ),
+    op(
+        "drop",
+        Op::Stack(StackOp::Drop),
+        "a --",
+        "Pop without printing",
+        ("1 2 drop", "1"),
+    ),
+    op(
+        "d",
+        Op::Stack(StackOp::Dup),
+        "a -- a a",
+        "Duplicate",
+        ("5 d", "5 5"),
+    ),
+    op(
+        "c",
+        Op::Stack(StackOp::Clear),
+        "... --",
+        "Clear the stack",
+        ("1 2 c", ""),
+    ),
+    op(
+        "r",
+        Op::Stack(StackOp::Rev),
+        "a b -- b a",
+        "Swap",
+        ("1 2 r", "2 1"),
+    ),
+    op(
+        "swap",
+        Op::Stack(StackOp::Rev),
+        "a b -- b a",
+        "Swap, as r",
+        ("1 2 swap", "2 1"),
+    ),
+    op(
+        "R",
+        Op::Stack(StackOp::Rotate),
+        "x1 ... xn n -- x2 ... xn x1",
+        "Rotate the top n items; negative n rotates the other way",
+        ("1 2 3 3 R", "2 3 1"),
+    ),
+    op(
+        "z",
+        Op::Stack(StackOp::Depth),
+        "-- n",
+        "Push the stack depth",
+        ("7 8 z", "7 8 2"),
+    ),
+    op(
+        "over",
+        Op::Stack(StackOp::Over),
+        "a b -- a b a",
+        "Copy the second item",
+        ("1 2 over", "1 2 1"),
+    ),
+    op(
+        "rot",
+        Op::Stack(StackOp::Rot),
+        "a b c -- b c a",
+        "Rotate the top three items",
+        ("1 2 3 rot", "2 3 1"),
+    ),
+    op(
+        "-rot",
+        Op::Stack(StackOp::RotBack),
+        "a b c -- c a b",
+        "Rotate the top three items back",
+        ("1 2 3 -rot", "3 1 2"),
+    ),
+    op(
+        "pick",
+        Op::Stack(StackOp::Pick),
+        "xn ... x0 n -- xn ... x0 xn",
+        "Copy the nth item; 0 pick is d",
+        ("1 2 3 2 pick", "1 2 3 1"),
+    ),
+    op(
+        "roll",
+        Op::Stack(StackOp::Roll),
+        "xn ... x0 n -- ... x0 xn",
+        "Move the nth item to the top; 2 roll is rot",
+        ("1 2 3 2 roll", "2 3 1"),
+    ),
+    op(
+        "nip",
+        Op::Stack(StackOp::Nip),
+        "a b -- b",
+        "Drop the second item",
+        ("1 2 nip", "2"),
+    ),
+    op(
+        "tuck",
+        Op::Stack(StackOp::Tuck),
+        "a b -- b a b",
+        "Copy the top item under the second",
+        ("1 2 tuck", "2 1 2"),
+    ),
+    op(
+        "dropn",
+        Op::Stack(StackOp::DropN),
+        "x1 ... xn n --",
+        "Drop n items",
+        ("1 2 3 2 dropn", "1"),
+    ),
+    op(
+        "dupn",
+        Op::Stack(StackOp::DupN),
+        "x1 ... xn n -- x1 ... xn x1 ... xn",
+        "Duplicate n items",
+        ("1 2 2 dupn", "1 2 1 2"),
+    ),
+    op(
+        "sort",
+        Op::Stack(StackOp::Sort),
+        "... -- ...",
+        "Sort the stack, largest on top",
+        ("3 1 2 sort", "1 2 3"),
+    ),
+    op(
+        "rsort",
+        Op::Stack(StackOp::RSort),
+        "... -- ...",
+        "Sort the stack, smallest on top",
+        ("3 1 2 rsort", "3 2 1"),
+    ),
+    op(
+        "uniq",
+        Op::Stack(StackOp::Unique),
+        "... -- ...",
+        "Drop repeated values, keeping the first",
+        ("1 2 1 3 2 uniq", "1 2 3"),
+    ),
+    op(
+        "sum",
+        Op::Stack(StackOp::Reduce(Reduce::Sum)),
+        "... -- sum",
+        "Sum the stack",
+        ("1 2 3 sum", "6"),
+    ),
+    op(
+        "prod",
+        Op::Stack(StackOp::Reduce(Reduce::Product)),
+        "... -- product",
+        "Multiply the stack",
+        ("2 3 4 prod", "24"),
+    ),
+    op(
+        "smin",
+        Op::Stack(StackOp::Reduce(Reduce::Min)),
+        "... -- min",
+        "Smallest item on the stack",
+        ("3 1 2 smin", "1"),
+    ),
+    op(
+        "smax",
+        Op::Stack(StackOp::Reduce(Reduce::Max)),
+        "... -- max",
+        "Largest item on the stack",
+        ("3 1 2 smax", "3"),
+    ),
+    op(
+        "mean",
+        Op::Stack(StackOp::Reduce(Reduce::Mean)),
+        "... -- mean",
+        "Mean of the stack, rounded towards zero",
+        ("1 2 4 mean", "2"),
+    ),
+    op(
+        "sxor",
+        Op::Stack(StackOp::Reduce(Reduce::Xor)),
+        "... -- xor",
+        "Xor the stack together",
+        ("1 2 3 sxor", "0"),
+    ),
+    op(
+        "sor",
+        Op::Stack(StackOp::Reduce(Reduce::Or)),
+        "... -- or",
+        "Or the stack together",
+        ("1 2 4 sor", "7"),
+    ),
+    op(
+        "sumn",
+        Op::Stack(StackOp::ReduceN(Reduce::Sum)),
+        "x1 ... xn n -- sum",
+        "Sum the top n items",
+        ("9 1 2 3 3 sumn", "9 6"),
+    ),
+    op(
+        "prodn",
+        Op::Stack(StackOp::ReduceN(Reduce::Product)),
+        "x1 ... xn n -- product",
+        "Multiply the top n items",
+        ("9 2 3 2 prodn", "9 6"),
+    ),
+    op(
+        "sminn",
+        Op::Stack(StackOp::ReduceN(Reduce::Min)),
+        "x1 ... xn n -- min",
+        "Smallest of the top n items",
+        ("9 3 1 2 sminn", "9 1"),
+    ),
+    op(
+        "smaxn",
+        Op::Stack(StackOp::ReduceN(Reduce::Max)),
+        "x1 ... xn n -- max",
+        "Largest of the top n items",
+        ("9 3 1 2 smaxn", "9 3"),
+    ),
+    op(
+        "meann",
+        Op::Stack(StackOp::ReduceN(Reduce::Mean)),
+        "x1 ... xn n -- mean",
+        "Mean of the top n items",
+        ("9 2 4 2 meann", "9 3"),
+    ),
+    op(
+        "sxorn",
+        Op::Stack(StackOp::ReduceN(Reduce::Xor)),
+        "x1 ... xn n -- xor",
+        "Xor the top n items together",
+        ("9 1 3 2 sxorn", "9 2"),
+    ),
+    op(
+        "sorn",
+        Op::Stack(StackOp::ReduceN(Reduce::Or)),
+        "x1 ... xn n -- or",
+        "Or the top n items together",
+        ("9 1 2 2 sorn", "9 3"),
+    ),
+    // Register operations
+    reg(
+        "s",
+        RegOp::Push,
+        "a --",
+        "Push onto a register; with no name, pop its number first",
+        ("5 sa 6 sa la", "6"),
+    ),
+    reg(
+        "l",
+        RegOp::Get,
+        "-- a",
+        "Copy the top of a register",
+        ("5 sa la la", "5 5"),
+    ),
+    reg(
+        "L",
+        RegOp::Pop,
+        "-- a",
+        "Pop a register onto the stack",
+        ("5 sa 6 sa La La", "6 5"),
+    ),
+    reg(
+        "F",
+        RegOp::Dump,
+        "--",
+        "Print a register's stack",
+        ("5 sa Fa", ""),
+    ),
+    reg("C", RegOp::Clear, "--", "Clear a register", ("5 sa Ca", "")),
+    reg(
+        ":",
+        RegOp::Store,
+        "a i --",
+        "Store a at index i of a register's array",
+        ("7 3 :a 3 ;a", "7"),
+    ),
+    reg(
+        ";",
+        RegOp::Load,
+        "i -- a",
+        "Load index i of a register's array, or 0",
+        ("3 ;a", "0"),
+    ),
+    op(
+        "clregs",
+        Op::ClearRegs,
+        "--",
+        "Clear every register",
+        ("5 sa clregs", ""),
+    ),
+    // Printing operations
+    op(
+        "p",
+        Op::Print(PrintOp::Print),
+        "a -- a",
+        "Print the top of the stack",
+        ("5 p", "5"),
+    ),
+    op(
+        "f",
+        Op::Print(PrintOp::Dump),
+        "--",
+        "Print the stack, bottom first",
+        ("1 2 f", "1 2"),
+    ),
+    op(
+        "o",
+        Op::Print(PrintOp::Output),
+        "radix --",
+        "Set the output radix: 2, 10 or 16",
+        ("16 o 255", "0xff"),
+    ),
+    op(
+        "hex",
+        Op::Print(PrintOp::Radix(Radix::Hex)),
+        "--",
+        "Print in hex, as 16 o",
+        ("hex 255", "0xff"),
+    ),
+    op(
+        "bin",
+        Op::Print(PrintOp::Radix(Radix::Bin)),
+        "--",
+        "Print in binary, as 2 o",
+        ("bin 5", "0b101"),
+    ),
+    op(
+        "dec",
+        Op::Print(PrintOp::Radix(Radix::Dec)),
+        "--",
+        "Print in decimal, as 10 o",
+        ("hex dec 255", "255"),
+    ),
+    op(
+        "regs",
+        Op::Print(PrintOp::Registers),
+        "--",
+        "List registers with their depth and top value",
+        ("5 sa regs", ""),
+    ),
+    op(
+        "words",
+        Op::Print(PrintOp::Words),
+        "--",
+        "List the user-defined words",
+        ("words", ""),
+    ),
+    op(
+        "help",
+        Op::Print(PrintOp::Help(None)),
+        "--",
+        "List every op",
+        NONE,
+    ),
+    arg(
+        "help:OP",
+        |op| Op::Print(PrintOp::Help(Some(op.into()))),
+        "--",
+        "Describe one op",
+        ("help:+", ""),
+    ),
+    // Casting operations
+    op(
+        "u",
+        Op::Cast(CastOp::U),
+        "a -- u",
+        "Cast to unsigned, keeping the bits",
+        ("-1 u", "18446744073709551615"),
+    ),
+    op(
+        "unsigned",
+        Op::Cast(CastOp::U),
+        "a -- u",
+        "Cast to unsigned, as u",
+        ("-1 unsigned", "18446744073709551615"),
+    ),
+    op(
+        "i",
+        Op::Cast(CastOp::I),
+        "a -- i",
+        "Cast to signed, keeping the bits",
+        ("18446744073709551615 i", "-1"),
+    ),
+    op(
+        "signed",
+        Op::Cast(CastOp::I),
+        "a -- i",
+        "Cast to signed, as i",
+        ("18446744073709551615 signed", "-1"),
+    ),
+    // Overflow modes
+    op(
+        "checked",
+        Op::Mode(Mode::Checked),
+        "--",
+        "Report overflow as an error (the default)",
+        ("checked", ""),
+    ),
+    op(
+        "wrapping",
+        Op::Mode(Mode::Wrapping),
+        "--",
+        "Wrap results that overflow",
+        ("wrapping 0 1 -", "18446744073709551615"),
+    ),
+    // Sessions
+    op(
+        "save",
+        Op::Session(SessionOp::Save(None)),
+        "--",
+        "Save the session to the session file",
+        NONE,
+    ),
+    op(
+        "load",
+        Op::Session(SessionOp::Load(None)),
+        "--",
+        "Load the session from the session file",
+        NONE,
+    ),
+    arg(
+        "save:PATH",
+        |path| Op::Session(SessionOp::Save(Some(path.into()))),
+        "--",
+        "Save the session to PATH",
+        NONE,
+    ),
+    arg(
+        "load:PATH",
+        |path| Op::Session(SessionOp::Load(Some(path.into()))),
+        "--",
+        "Load the session from PATH",
+        NONE,
+    ),
+    // Words
+    arg(
+        "forget:NAME",
+        |name| Op::Forget(name.into()),
+        "--",
+        "Forget a word defined with : NAME ... ;",
+        (": w 1 ; forget:w", ""),
+    ),
+    // Input
+    op(
+        "?",
+        Op::Input(InputOp::Line),
+        "--",
+        "Read a line of input and run it",
+        NONE,
+    ),
+    op(
+        "??",
+        Op::Input(InputOp::Numbers),
+        "-- ...",
+        "Read a line of input and push its numbers",
+        NONE,
+    ),
+    // Debugging
+    op(
+        "trace",
+        Op::Debug(DebugOp::Trace(true)),
+        "--",
+        "Print each op with the stack before and after",
+        ("trace notrace", ""),
+    ),
+    op(
+        "notrace",
+        Op::Debug(DebugOp::Trace(false)),
+        "--",
+        "Stop tracing",
+        ("notrace", ""),
+    ),
+    op(
+        "step",
+        Op::Debug(DebugOp::Step),
+        "--",
+        "Stop in the debugger before the next op",
+        NONE,
+    ),
+    op(
+        "breaks",
+        Op::Debug(DebugOp::Breakpoints),
+        "--",
+        "List the breakpoints",
+        ("breaks", ""),
+    ),
+    arg(
+        "break:NAME",
+        |name| Op::Debug(DebugOp::Break(name.into())),
+        "--",
+        "Stop in the debugger when a word runs",
+        ("break:w unbreak:w", ""),
+    ),
+    arg(
+        "unbreak:NAME",
+        |name| Op::Debug(DebugOp::Unbreak(name.into())),
+        "--",
+        "Remove a breakpoint",
+        ("unbreak:w", ""),
+    ),
+    // Control
+    op("q", Op::Quit, "--", "Quit", NONE),
+    op(
+        "Q",
+        Op::QuitWords,
+        "n --",
+        "Return from n levels of words",
+        (": w 1 2 Q 2 ; w 3", "1 3"),
+    ),
+];
+
+/// The ops `--dc-compat` reads in place of the ones in `OPS` with the same
+/// token, to give single letters their GNU dc meanings.
+pub static DC_OPS: &[OpInfo] = &[
+    op(
+        "n",
+        Op::Print(PrintOp::Write),
+        "a --",
+        "Pop and print without a newline",
+        ("1 2 n", "1"),
+    ),
+    op(
+        "f",
+        Op::Print(PrintOp::DumpTopFirst),
+        "--",
+        "Print the stack, top first",
+        ("1 2 f", "1 2"),
+    ),
+    op(
+        "i",
+        Op::InputRadix,
+        "radix --",
+        "Set the input radix: 2, 10 or 16",
+        ("16 i FF _A", "255 -10"),
+    ),
+    reg(
+        "s",
+        RegOp::Replace,
+        "a --",
+        "Replace the top of a register",
+        ("1 Sa 2 Sa 3 sa La La", "3 1"),
+    ),
+    reg(
+        "S",
+        RegOp::Push,
+        "a --",
+        "Push onto a register",
+        ("1 Sa 2 Sa La La", "2 1"),
+    ),
+];
@@ -0,0 +1,142 @@
+use crate::error::Diagnostic;
+use crate::stack::op::{CalcOp, Dialect, Op, StackOp};
+use crate::stack::val::Val;
+use crate::stack::Stack;
+
+/// One token of a program.
+pub struct Instr {
+    /// The column the token starts at, counting from 1.
+    pub col: usize,
+    pub token: String,
+    /// The parsed op, or None if the token must be run with `Stack::eval`.
+    op: Option<Op>,
+    /// The tokens before `token` that were folded into `op`, so a definition
+    /// left open when the program runs can collect the source as written.
+    folded: Vec<String>,
+}
+
+/// A line of input split into tokens, and optionally compiled so it can be
+/// run many times without parsing each token again.
+pub struct Program {
+    code: Vec<Instr>,
+}
+
+impl Program {
+    /// Splits `line` into tokens, to be parsed as they run.
+    pub fn parse(line: &str) -> Self {
+        let code = tokens(line)
+            .map(|(col, token)| Instr {
+                col,
+                token: token.into(),
+                op: None,
+                folded: Vec::new(),
+            })
+            .collect();
+        Program { code }
+    }
+
+    /// Parses each token of `line` into an op once. Register names are parsed
+    /// into ids and numbers into values, and calculations on numbers that
+    /// can't fail, like `1024 4 *`, are done here instead of on every run.
+    /// Word definitions are left to run as tokens, since they change how
+    /// later tokens are read, and so are dc numbers, since the program may
+    /// change the input radix they are read in.
+    pub fn compile(line: &str, dialect: Dialect) -> Self {
+        let mut code: Vec<Instr> = Vec::new();
+        let mut defining = false;
+        for (col, token) in tokens(line) {
+            defining = match token {
+                ":" => true,
+                ";" => false,
+                _ => defining,
+            };
+            let op = match token {
+                ":" | ";" => None,
+                _ if defining || dialect.reads_radix(token) => None,
+                _ => Op::parse(token, dialect).ok(),
+            };
+            let (op, folded) = match op {
+                Some(Op::Calc(calc)) => match fold(&mut code, calc) {
+                    Some((val, folded)) => (Some(Op::Stack(StackOp::Push(val))), folded),
+                    None => (Some(Op::Calc(calc)), Vec::new()),
+                },
+                op => (op, Vec::new()),
+            };
+            code.push(Instr {
+                col,
+                token: token.into(),
+                op,
+                folded,
+            });
+        }
+        Program { code }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Instr> {
+        self.code.iter()
+    }
+}
+
+/// If `calc` only needs numbers pushed at the end of `code`, and works on them
+/// without error, removes them and returns the result with the tokens they
+/// came from. Calculations that succeed when checked give the same result
+/// when wrapping, so the result doesn't depend on the mode the program runs in.
+fn fold(code: &mut Vec<Instr>, calc: CalcOp) -> Option<(Val, Vec<String>)> {
+    let mut scratch = Stack::new();
+    for instr in code.iter().rev().take(2) {
+        match &instr.op {
+            Some(Op::Stack(StackOp::Push(val))) => scratch.stack.insert(0, val.clone()),
+            _ => break,
+        }
+    }
+    let literals = scratch.stack.len();
+    scratch.calc_op(calc).ok()?;
+    let used = literals + 1 - scratch.stack.len();
+    let folded = code
+        .drain(code.len() - used..)
+        .flat_map(|instr| instr.folded.into_iter().chain([instr.token]))
+        .collect();
+    Some((scratch.stack.pop()?, folded))
+}
+
+/// Splits `line` on whitespace, with the column each token starts at.
+fn tokens(line: &str) -> impl Iterator<Item = (usize, &str)> {
+    let mut col = 0;
+    let mut start = None;
+    let mut chars = line.char_indices().chain([(line.len(), ' ')]);
+    std::iter::from_fn(move || {
+        for (i, c) in chars.by_ref() {
+            col += 1;
+            match (start, c.is_whitespace()) {
+                (None, false) => start = Some((i, col)),
+                (Some((begin, begin_col)), true) => {
+                    start = None;
+                    return Some((begin_col, &line[begin..i]));
+                }
+                _ => (),
+            }
+        }
+        None
+    })
+}
+
+impl Stack {
+    /// Runs one instruction of a program, as `eval` runs a token. Compiled
+    /// ops skip parsing but nothing else, so they are still collected into
+    /// an open definition and count against the limits. An open definition
+    /// collects a folded calculation as the tokens it was folded from.
+    pub fn eval_instr(&mut self, instr: &Instr) -> Result<bool, Diagnostic> {
+        match &instr.op {
+            Some(_) if self.defining.is_some() && !instr.folded.is_empty() => {
+                for token in instr.folded.iter() {
+                    self.eval(token)?;
+                }
+                self.eval(&instr.token)
+            }
+            Some(op) => self.diagnose(&instr.token, |stack| {
+                stack.run_token(&instr.token, Some(op.clone()))
+            }),
+            None => self.eval(&instr.token),
+        }
+    }
+}
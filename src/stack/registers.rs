@@ -1,9 +1,86 @@
 use crate::error::Error::{self, EndOfRegister};
 use crate::stack::val::Val;
-use std::collections::HashMap;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+/// A register is named either by a value popped from the stack (`13 s`) or
+/// by a name given with the operator (`sa`, `s:counter`).
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub enum RegId {
+    Num(Val),
+    Name(String),
+}
+
+impl fmt::Display for RegId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RegId::Num(Val::U64(u)) => f.pad(&u.to_string()),
+            RegId::Num(Val::I64(i)) => f.pad(&i.to_string()),
+            RegId::Name(name) => f.pad(name),
+        }
+    }
+}
+
+/// Numeric ids sort before names, numerically and then unsigned first.
+impl Ord for RegId {
+    fn cmp(&self, other: &RegId) -> Ordering {
+        let signed = |v: &Val| matches!(v, Val::I64(_));
+        match (self, other) {
+            (RegId::Num(v1), RegId::Num(v2)) => {
+                v1.compare(v2).then_with(|| signed(v1).cmp(&signed(v2)))
+            }
+            (RegId::Num(_), RegId::Name(_)) => Ordering::Less,
+            (RegId::Name(_), RegId::Num(_)) => Ordering::Greater,
+            (RegId::Name(n1), RegId::Name(n2)) => n1.cmp(n2),
+        }
+    }
+}
+
+impl PartialOrd for RegId {
+    fn partial_cmp(&self, other: &RegId) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// A summary of one register, as listed by `regs`.
+pub struct RegInfo<'a> {
+    pub id: &'a RegId,
+    pub depth: usize,
+    pub top: Option<&'a Val>,
+    pub elements: usize,
+}
+
+/// A sparse array indexed by integer, as set by `:r` and read by `;r`.
+pub type Array = BTreeMap<u64, Val>;
+
+/// A value pushed onto a register, with the array that goes with it.
+pub type Level = (Val, Array);
+
+#[derive(Default)]
+struct Register {
+    /// Each value pushed onto the register gets its own array, which is
+    /// discarded again when the value is popped.
+    stack: Vec<Level>,
+    /// The array in use while the register stack is empty.
+    array: Array,
+}
+
+impl Register {
+    fn array(&self) -> &Array {
+        self.stack.last().map_or(&self.array, |(_, array)| array)
+    }
+
+    fn array_mut(&mut self) -> &mut Array {
+        match self.stack.last_mut() {
+            Some((_, array)) => array,
+            None => &mut self.array,
+        }
+    }
+}
 
 pub struct Registers {
-    reg: HashMap<Val, Vec<Val>>,
+    reg: HashMap<RegId, Register>,
 }
 
 impl Registers {
@@ -13,26 +90,95 @@ impl Registers {
         }
     }
 
-    pub fn push(&mut self, id: Val, val: Val) {
-        self.reg.entry(id).or_insert_with(Vec::new).push(val);
+    pub fn push(&mut self, id: RegId, val: Val) {
+        let reg = self.reg.entry(id).or_default();
+        reg.stack.push((val, Array::new()));
+    }
+
+    /// Replaces the top value of the register, keeping its array. An empty
+    /// register takes `val` with the array it already has, as in dc.
+    pub fn set(&mut self, id: RegId, val: Val) {
+        let reg = self.reg.entry(id).or_default();
+        match reg.stack.last_mut() {
+            Some((top, _)) => *top = val,
+            None => {
+                let array = std::mem::take(&mut reg.array);
+                reg.stack.push((val, array));
+            }
+        }
     }
 
-    pub fn pop(&mut self, id: &Val) -> Result<Val, Error> {
+    pub fn pop(&mut self, id: &RegId) -> Result<Val, Error> {
         self.reg
             .get_mut(id)
-            .and_then(|stack| stack.pop())
+            .and_then(|reg| reg.stack.pop())
+            .map(|(val, _)| val)
             .ok_or_else(|| EndOfRegister(id.to_string()))
     }
 
-    pub fn val(&self, id: &Val) -> Result<Val, Error> {
+    pub fn val(&self, id: &RegId) -> Result<Val, Error> {
         self.reg
             .get(id)
-            .and_then(|stack| stack.last())
-            .cloned()
+            .and_then(|reg| reg.stack.last())
+            .map(|(val, _)| val.clone())
             .ok_or_else(|| EndOfRegister(id.to_string()))
     }
 
-    pub fn iter(&self, id: &Val) -> Option<impl Iterator<Item = &Val>> {
-        self.reg.get(id).map(|stack| stack.iter())
+    pub fn iter(&self, id: &RegId) -> Option<impl Iterator<Item = &Val>> {
+        self.reg
+            .get(id)
+            .map(|reg| reg.stack.iter().map(|(val, _)| val))
+    }
+
+    pub fn store(&mut self, id: RegId, index: u64, val: Val) {
+        let reg = self.reg.entry(id).or_default();
+        reg.array_mut().insert(index, val);
+    }
+
+    /// Empties a register's stack and arrays.
+    pub fn clear(&mut self, id: &RegId) {
+        self.reg.remove(id);
+    }
+
+    pub fn clear_all(&mut self) {
+        self.reg.clear();
+    }
+
+    /// Every register that holds a value or array element, sorted by id.
+    pub fn list(&self) -> Vec<RegInfo<'_>> {
+        let mut list: Vec<RegInfo> = self
+            .reg
+            .iter()
+            .map(|(id, reg)| RegInfo {
+                id,
+                depth: reg.stack.len(),
+                top: reg.stack.last().map(|(val, _)| val),
+                elements: reg.array().len(),
+            })
+            .filter(|info| info.depth > 0 || info.elements > 0)
+            .collect();
+        list.sort_by(|i1, i2| i1.id.cmp(i2.id));
+        list
+    }
+
+    /// Every register sorted by id, with the array used while its stack is
+    /// empty and its stacked values, bottom first, each with its own array.
+    pub fn levels(&self) -> Vec<(&RegId, &Array, &[Level])> {
+        let mut levels: Vec<_> = self
+            .reg
+            .iter()
+            .map(|(id, reg)| (id, &reg.array, reg.stack.as_slice()))
+            .collect();
+        levels.sort_by(|l1, l2| l1.0.cmp(l2.0));
+        levels
+    }
+
+    /// Reads an array element. Elements that were never stored read as zero, as in dc.
+    pub fn load(&self, id: &RegId, index: u64) -> Val {
+        self.reg
+            .get(id)
+            .and_then(|reg| reg.array().get(&index))
+            .cloned()
+            .unwrap_or(Val::U64(0))
     }
 }
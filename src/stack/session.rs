@@ -0,0 +1,196 @@
+use crate::error::Error;
+use crate::stack::op::{Dialect, Radix};
+use crate::stack::registers::RegId;
+use crate::stack::val::{Mode, Val};
+use crate::stack::Stack;
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Session files start with this header and a version number, followed by
+/// one setting, stack value, register value or word per line:
+///
+/// ```text
+/// dcrs-session 3
+/// output 16
+/// mode checked
+/// stack u64 5
+/// reg name:a i64 -3
+/// array name:a 7 u64 1
+/// word kib 1024 *
+/// dialect dc 16
+/// word neg5 _5
+/// dialect dcrs
+/// ```
+///
+/// `array` lines store into the array of the register's most recent `reg`
+/// line, so the file replays exactly as `s` and `:` would. A `dialect` line
+/// sets how the `word` lines after it are read, and the last one is the
+/// session's own. Loading keeps whether dc's dialect is on, as set by
+/// `--dc-compat`, and only takes the saved input radix if it is. Older
+/// versions, written before `word` (1) and `dialect` (2) lines were added,
+/// can still be loaded.
+const HEADER: &str = "dcrs-session";
+const VERSION: u32 = 3;
+
+/// `$XDG_DATA_HOME/dcrs/session`, falling back to `~/.local/share`.
+pub fn default_path() -> Option<PathBuf> {
+    let data = env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")))?;
+    Some(data.join("dcrs").join("session"))
+}
+
+impl Stack {
+    /// Writes the stack, registers, words and settings to `path`.
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        let mut out = String::new();
+        writeln!(out, "{} {}", HEADER, VERSION).unwrap();
+        writeln!(out, "output {}", write_radix(self.output)).unwrap();
+        let mode = match self.mode {
+            Mode::Checked => "checked",
+            Mode::Wrapping => "wrapping",
+        };
+        writeln!(out, "mode {}", mode).unwrap();
+        for val in self.stack.iter() {
+            writeln!(out, "stack {}", write_val(val)).unwrap();
+        }
+        for (id, array, stack) in self.reg.levels() {
+            let id = write_id(id);
+            for (index, val) in array.iter() {
+                writeln!(out, "array {} {} {}", id, index, write_val(val)).unwrap();
+            }
+            for (val, array) in stack.iter() {
+                writeln!(out, "reg {} {}", id, write_val(val)).unwrap();
+                for (index, val) in array.iter() {
+                    writeln!(out, "array {} {} {}", id, index, write_val(val)).unwrap();
+                }
+            }
+        }
+        let mut dialect = Dialect::Dcrs;
+        for (name, word) in self.words.iter() {
+            if word.dialect != dialect {
+                dialect = word.dialect;
+                writeln!(out, "dialect {}", write_dialect(dialect)).unwrap();
+            }
+            writeln!(out, "word {} {}", name, word.src).unwrap();
+        }
+        writeln!(out, "dialect {}", write_dialect(self.dialect)).unwrap();
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, out)?;
+        Ok(())
+    }
+
+    /// Replaces the stack, registers, words and settings with those saved in
+    /// `path`.
+    /// Nothing changes if the file can't be read or parsed.
+    pub fn load(&mut self, path: &Path) -> Result<(), Error> {
+        let text = fs::read_to_string(path)?;
+        let mut lines = text.lines().enumerate();
+        let bad =
+            |n: usize, msg: &str| Error::Session(format!("{}:{}: {}", path.display(), n + 1, msg));
+        let version = match lines.next().and_then(|(_, line)| line.split_once(' ')) {
+            Some((HEADER, version)) => version.parse().unwrap_or(0),
+            _ => return Err(bad(0, "not a session file")),
+        };
+        if !(1..=VERSION).contains(&version) {
+            return Err(bad(0, "unsupported version"));
+        }
+        let mut session = Stack::new();
+        for (n, line) in lines {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            session.load_line(line).ok_or_else(|| bad(n, line))?;
+        }
+        if session.stack.len() > self.limits.stack {
+            return Err(Error::StackLimit(self.limits.stack));
+        }
+        self.stack = session.stack;
+        self.reg = session.reg;
+        self.output = session.output;
+        self.mode = session.mode;
+        self.words = session.words;
+        if let (Dialect::Dc(_), Dialect::Dc(input)) = (self.dialect, session.dialect) {
+            self.dialect = Dialect::Dc(input);
+        }
+        Ok(())
+    }
+
+    fn load_line(&mut self, line: &str) -> Option<()> {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        match fields.as_slice() {
+            ["output", radix] => {
+                let radix: Option<Radix> = radix.parse::<u64>().ok().map(Val::U64)?.into();
+                self.output = radix?;
+            }
+            ["dialect", "dcrs"] => self.dialect = Dialect::Dcrs,
+            ["dialect", "dc", radix] => {
+                let radix: Option<Radix> = radix.parse::<u64>().ok().map(Val::U64)?.into();
+                self.dialect = Dialect::Dc(radix?);
+            }
+            ["mode", "checked"] => self.mode = Mode::Checked,
+            ["mode", "wrapping"] => self.mode = Mode::Wrapping,
+            ["stack", ty, val] => self.stack.push(read_val(ty, val)?),
+            ["reg", id, ty, val] => self.reg.push(read_id(id)?, read_val(ty, val)?),
+            ["array", id, index, ty, val] => {
+                self.reg
+                    .store(read_id(id)?, index.parse().ok()?, read_val(ty, val)?)
+            }
+            ["word", name, body @ ..] => {
+                let body: Vec<String> = body.iter().map(|token| token.to_string()).collect();
+                self.words.define(name, &body, self.dialect).ok()?
+            }
+            _ => return None,
+        }
+        Some(())
+    }
+}
+
+fn write_radix(radix: Radix) -> u32 {
+    match radix {
+        Radix::Bin => 2,
+        Radix::Dec => 10,
+        Radix::Hex => 16,
+    }
+}
+
+fn write_dialect(dialect: Dialect) -> String {
+    match dialect {
+        Dialect::Dcrs => "dcrs".into(),
+        Dialect::Dc(input) => format!("dc {}", write_radix(input)),
+    }
+}
+
+fn write_val(val: &Val) -> String {
+    match val {
+        Val::U64(u) => format!("u64 {}", u),
+        Val::I64(i) => format!("i64 {}", i),
+    }
+}
+
+fn read_val(ty: &str, val: &str) -> Option<Val> {
+    match ty {
+        "u64" => val.parse().ok().map(Val::U64),
+        "i64" => val.parse().ok().map(Val::I64),
+        _ => None,
+    }
+}
+
+fn write_id(id: &RegId) -> String {
+    match id {
+        RegId::Num(val) => write_val(val).replacen(' ', ":", 1),
+        RegId::Name(name) => format!("name:{}", name),
+    }
+}
+
+fn read_id(id: &str) -> Option<RegId> {
+    match id.split_once(':')? {
+        ("name", name) if !name.is_empty() => Some(RegId::Name(name.into())),
+        (ty, val) => read_val(ty, val).map(RegId::Num),
+    }
+}
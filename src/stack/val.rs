@@ -1,7 +1,7 @@
 use crate::error::Error;
+use std::cmp::Ordering;
 use std::convert::From;
 use std::fmt;
-use std::ops;
 use std::str::FromStr;
 use termion::style;
 use Val::*;
@@ -14,10 +14,7 @@ pub enum Val {
 
 impl Val {
     pub fn is_zero(&self) -> bool {
-        match self {
-            Val::U64(u) => *u == 0u64,
-            Val::I64(i) => *i == 0i64,
-        }
+        self.wide() == 0
     }
 }
 
@@ -48,357 +45,428 @@ impl fmt::Binary for Val {
     }
 }
 
-impl Val {
-    pub fn pow(self, other: Val) -> Val {
-        match self {
-            U64(u) => U64(u.pow(other.into())),
-            I64(i) => I64(i.pow(other.into())),
+/// Formats a value without styling, padding or type suffix, for output that
+/// isn't going to a terminal.
+pub struct Plain<'a>(pub &'a Val);
+
+impl fmt::Display for Plain<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            U64(u) => write!(f, "{}", u),
+            I64(i) => write!(f, "{}", i),
         }
     }
 }
 
-impl From<Val> for i32 {
-    fn from(v: Val) -> i32 {
-        match v {
-            U64(u) => u as i32,
-            I64(i) => i as i32,
+impl fmt::LowerHex for Plain<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            U64(u) => write!(f, "{:#x}", u),
+            I64(i) => write!(f, "{:#x}", i),
         }
     }
 }
 
-impl From<Val> for u32 {
-    fn from(v: Val) -> u32 {
-        match v {
-            U64(u) => u as u32,
-            I64(i) => i as u32,
+impl fmt::Binary for Plain<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            U64(u) => write!(f, "{:#b}", u),
+            I64(i) => write!(f, "{:#b}", i),
         }
     }
 }
 
-impl From<Val> for u64 {
-    fn from(v: Val) -> u64 {
-        match v {
-            U64(u) => u,
-            I64(i) => i as u64,
-        }
-    }
+/// How arithmetic handles results that don't fit the result type.
+///
+/// Every op promotes its operands to `i128` with `wide`, so every `U64` and
+/// `I64` value keeps its meaning, and narrows the result back with `narrow`:
+/// to `U64` if both operands were `U64` and to `I64` otherwise. Bitwise
+/// operations use the low 64 bits, the two's complement pattern, and are
+/// always narrowed by truncating, so they cannot overflow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// Report `Error::Overflow`.
+    Checked,
+    /// Truncate to the low 64 bits of the result. Shifting by 64 bits or
+    /// more fills with zeros, or with the sign bit for arithmetic shifts.
+    Wrapping,
 }
 
-impl From<Val> for i64 {
-    fn from(v: Val) -> i64 {
-        match v {
-            U64(u) => u as i64,
-            I64(i) => i,
-        }
+impl Val {
+    pub fn add(self, rhs: Val, mode: Mode) -> Result<Val, Error> {
+        self.arith(rhs, mode, i128::overflowing_add)
     }
-}
 
-impl ops::Add<Val> for u64 {
-    type Output = Val;
-    fn add(self, rhs: Val) -> Val {
-        match rhs {
-            U64(u) => U64(self + u),
-            I64(i) => I64(self as i64 + i),
-        }
+    pub fn sub(self, rhs: Val, mode: Mode) -> Result<Val, Error> {
+        self.arith(rhs, mode, i128::overflowing_sub)
     }
-}
 
-impl ops::Add<Val> for i64 {
-    type Output = Val;
-    fn add(self, rhs: Val) -> Val {
-        match rhs {
-            U64(u) => I64(self + u as i64),
-            I64(i) => I64(self + i),
-        }
+    pub fn mul(self, rhs: Val, mode: Mode) -> Result<Val, Error> {
+        self.arith(rhs, mode, i128::overflowing_mul)
     }
-}
 
-impl ops::Add for Val {
-    type Output = Val;
-    fn add(self, rhs: Val) -> Val {
-        match self {
-            U64(u) => u + rhs,
-            I64(i) => i + rhs,
+    pub fn div(self, rhs: Val, mode: Mode) -> Result<Val, Error> {
+        if rhs.is_zero() {
+            return Err(Error::DivideByZero);
         }
+        self.arith(rhs, mode, i128::overflowing_div)
     }
-}
 
-impl ops::Sub<Val> for u64 {
-    type Output = Val;
-    fn sub(self, rhs: Val) -> Val {
-        match rhs {
-            U64(u) => U64(self - u),
-            I64(i) => I64(self as i64 - i),
+    pub fn rem(self, rhs: Val, mode: Mode) -> Result<Val, Error> {
+        if rhs.is_zero() {
+            return Err(Error::DivideByZero);
         }
+        self.arith(rhs, mode, i128::overflowing_rem)
     }
-}
 
-impl ops::Sub<Val> for i64 {
-    type Output = Val;
-    fn sub(self, rhs: Val) -> Val {
-        match rhs {
-            U64(u) => I64(self - u as i64),
-            I64(i) => I64(self - i),
-        }
+    /// Raises the value to the power `other`. The result has the type of the
+    /// base; the exponent doesn't take part in promotion. Negative exponents
+    /// truncate towards zero, as integer division does.
+    pub fn pow(self, other: Val, mode: Mode) -> Result<Val, Error> {
+        let (base, signed) = (self.wide(), self.is_signed());
+        let (wide, overflow) = match other {
+            U64(exp) => pow_wide(base, exp),
+            I64(exp) if exp >= 0 => pow_wide(base, exp as u64),
+            I64(exp) => match base {
+                0 => return Err(Error::DivideByZero),
+                -1 if exp % 2 != 0 => (-1, false),
+                1 | -1 => (1, false),
+                _ => (0, false),
+            },
+        };
+        Val::narrow(wide, overflow, signed, mode)
+    }
+
+    /// Shifts left. The result has the type of the shifted value.
+    pub fn shl(self, amount: Val, mode: Mode) -> Result<Val, Error> {
+        let n = shift_amount(amount, mode)?;
+        Ok(self.map_bits(|a| a << n))
     }
-}
 
-impl ops::Sub for Val {
-    type Output = Val;
-    fn sub(self, rhs: Val) -> Val {
+    /// Shifts right: arithmetically for `I64` and logically for `U64`.
+    pub fn shr(self, amount: Val, mode: Mode) -> Result<Val, Error> {
         match self {
-            U64(u) => u - rhs,
-            I64(i) => i - rhs,
+            U64(_) => self.lsr(amount, mode),
+            I64(_) => self.asr(amount, mode),
         }
     }
-}
 
-impl ops::Mul<Val> for u64 {
-    type Output = Val;
-    fn mul(self, rhs: Val) -> Val {
-        match rhs {
-            U64(u) => U64(self * u),
-            I64(i) => I64(self as i64 * i),
-        }
+    /// Logical shift right, filling with zeros regardless of type.
+    pub fn lsr(self, amount: Val, mode: Mode) -> Result<Val, Error> {
+        let n = shift_amount(amount, mode)?;
+        Ok(self.map_bits(|a| (a & LOW_BITS) >> n))
     }
-}
 
-impl ops::Mul<Val> for i64 {
-    type Output = Val;
-    fn mul(self, rhs: Val) -> Val {
-        match rhs {
-            U64(u) => I64(self * u as i64),
-            I64(i) => I64(self * i),
-        }
+    /// Arithmetic shift right, filling with the sign bit regardless of type.
+    pub fn asr(self, amount: Val, mode: Mode) -> Result<Val, Error> {
+        let n = shift_amount(amount, mode)?;
+        // Moving bit 63 to the top of the i128 and back copies it upwards.
+        Ok(self.map_bits(|a| (a << 64 >> 64) >> n))
     }
-}
 
-impl ops::Mul for Val {
-    type Output = Val;
-    fn mul(self, rhs: Val) -> Val {
-        match self {
-            U64(u) => u * rhs,
-            I64(i) => i * rhs,
-        }
+    pub fn and(self, rhs: Val) -> Val {
+        self.bits(rhs, |a, b| a & b)
     }
-}
 
-impl ops::Div<Val> for u64 {
-    type Output = Val;
-    fn div(self, rhs: Val) -> Val {
-        match rhs {
-            U64(u) => U64(self / u),
-            I64(i) => I64(self as i64 / i),
-        }
+    pub fn or(self, rhs: Val) -> Val {
+        self.bits(rhs, |a, b| a | b)
     }
-}
 
-impl ops::Div<Val> for i64 {
-    type Output = Val;
-    fn div(self, rhs: Val) -> Val {
-        match rhs {
-            U64(u) => I64(self / u as i64),
-            I64(i) => I64(self / i),
-        }
+    pub fn xor(self, rhs: Val) -> Val {
+        self.bits(rhs, |a, b| a ^ b)
     }
-}
 
-impl ops::Div for Val {
-    type Output = Val;
-    fn div(self, rhs: Val) -> Val {
-        match self {
-            U64(u) => u / rhs,
-            I64(i) => i / rhs,
-        }
+    pub fn not(self) -> Val {
+        self.map_bits(|a| !a)
     }
-}
 
-impl ops::Rem<Val> for u64 {
-    type Output = Val;
-    fn rem(self, rhs: Val) -> Val {
-        match rhs {
-            U64(u) => U64(self % u),
-            I64(i) => I64(self as i64 % i),
-        }
+    /// Rotates left, by the amount modulo 64. Negative amounts rotate right.
+    pub fn rotl(self, amount: Val) -> Val {
+        let n = amount.wide().rem_euclid(64) as u32;
+        self.map_bits(|a| ((a & LOW_BITS) << n) | ((a & LOW_BITS) >> (64 - n)))
     }
-}
 
-impl ops::Rem<Val> for i64 {
-    type Output = Val;
-    fn rem(self, rhs: Val) -> Val {
-        match rhs {
-            U64(u) => I64(self % u as i64),
-            I64(i) => I64(self % i),
-        }
+    /// Rotates right, by the amount modulo 64. Negative amounts rotate left.
+    pub fn rotr(self, amount: Val) -> Val {
+        let n = amount.wide().rem_euclid(64) as u32;
+        self.map_bits(|a| ((a & LOW_BITS) >> n) | ((a & LOW_BITS) << (64 - n)))
     }
-}
 
-impl ops::Rem for Val {
-    type Output = Val;
-    fn rem(self, rhs: Val) -> Val {
-        match self {
-            U64(u) => u % rhs,
-            I64(i) => i % rhs,
-        }
+    /// The number of set bits.
+    pub fn popcnt(self) -> Val {
+        U64((self.wide() & LOW_BITS).count_ones() as u64)
     }
-}
 
-impl ops::BitAnd<Val> for u64 {
-    type Output = Val;
-    fn bitand(self, rhs: Val) -> Val {
-        match rhs {
-            U64(u) => U64(self & u),
-            I64(i) => I64(self as i64 & i),
-        }
+    /// The number of leading zero bits, 64 for zero.
+    pub fn clz(self) -> Val {
+        U64((self.wide() & LOW_BITS).leading_zeros() as u64 - 64)
+    }
+
+    /// The number of trailing zero bits, 64 for zero.
+    pub fn ctz(self) -> Val {
+        U64((self.wide() & LOW_BITS).trailing_zeros().min(64) as u64)
     }
-}
 
-impl ops::BitAnd<Val> for i64 {
-    type Output = Val;
-    fn bitand(self, rhs: Val) -> Val {
-        match rhs {
-            U64(u) => I64(self & u as i64),
-            I64(i) => I64(self & i),
+    /// Applies an arithmetic op, `f`, to both values promoted to `i128`. The
+    /// result type follows the promotion rules in `Mode`.
+    fn arith(self, rhs: Val, mode: Mode, f: fn(i128, i128) -> (i128, bool)) -> Result<Val, Error> {
+        let (wide, overflow) = f(self.wide(), rhs.wide());
+        Val::narrow(wide, overflow, self.is_signed() || rhs.is_signed(), mode)
+    }
+
+    /// Converts an `i128` result back to a `Val`. `overflow` is set if the
+    /// result already overflowed the `i128`, in which case its low 64 bits
+    /// are still correct.
+    fn narrow(wide: i128, overflow: bool, signed: bool, mode: Mode) -> Result<Val, Error> {
+        match (mode, signed) {
+            (Mode::Wrapping, true) => Ok(I64(wide as i64)),
+            (Mode::Wrapping, false) => Ok(U64(wide as u64)),
+            _ if overflow => Err(Error::Overflow),
+            (Mode::Checked, true) => i64::try_from(wide).map(I64).map_err(|_| Error::Overflow),
+            (Mode::Checked, false) => u64::try_from(wide).map(U64).map_err(|_| Error::Overflow),
         }
     }
+
+    /// Applies a bitwise op, `f`, to both values promoted to `i128`, keeping
+    /// the low 64 bits of the result. The result type follows the same rule
+    /// as arithmetic, but can't overflow.
+    fn bits(self, rhs: Val, f: fn(i128, i128) -> i128) -> Val {
+        let signed = self.is_signed() || rhs.is_signed();
+        Val::truncate(f(self.wide(), rhs.wide()), signed)
+    }
+
+    /// Applies a bitwise op, `f`, to the value promoted to `i128`, keeping the
+    /// low 64 bits of the result and the value's type.
+    fn map_bits(self, f: impl Fn(i128) -> i128) -> Val {
+        let signed = self.is_signed();
+        Val::truncate(f(self.wide()), signed)
+    }
+
+    /// Narrows a result that only needs its low 64 bits.
+    fn truncate(wide: i128, signed: bool) -> Val {
+        Val::narrow(wide, false, signed, Mode::Wrapping).expect("wrapping can't overflow")
+    }
+
+    fn is_signed(&self) -> bool {
+        matches!(self, I64(_))
+    }
 }
 
-impl ops::BitAnd for Val {
-    type Output = Val;
-    fn bitand(self, rhs: Val) -> Val {
-        match self {
-            U64(u) => u & rhs,
-            I64(i) => i & rhs,
+impl Val {
+    /// Integer square root, rounded down.
+    pub fn sqrt(self) -> Result<Val, Error> {
+        match self.wide() {
+            w if w < 0 => Err(Error::Domain("square root of a negative number")),
+            w => self.exact(w.isqrt()),
         }
     }
-}
 
-impl ops::BitOr<Val> for u64 {
-    type Output = Val;
-    fn bitor(self, rhs: Val) -> Val {
-        match rhs {
-            U64(u) => U64(self | u),
-            I64(i) => I64(self as i64 | i),
+    /// Integer nth root, rounded towards zero. Odd roots of negative numbers
+    /// are negative; even roots of negative numbers are an error.
+    pub fn root(self, n: Val) -> Result<Val, Error> {
+        let n = match n.wide() {
+            n if n < 0 => return Err(Error::Domain("negative root")),
+            0 => return Err(Error::Domain("zeroth root")),
+            n => n,
+        };
+        match self.wide() {
+            w if w >= 0 => self.exact(iroot(w, n)),
+            w if n % 2 == 1 => self.exact(-iroot(-w, n)),
+            _ => Err(Error::Domain("even root of a negative number")),
         }
     }
-}
 
-impl ops::BitOr<Val> for i64 {
-    type Output = Val;
-    fn bitor(self, rhs: Val) -> Val {
-        match rhs {
-            U64(u) => I64(self | u as i64),
-            I64(i) => I64(self | i),
+    /// Base 2 logarithm, rounded down.
+    pub fn log2(self) -> Result<Val, Error> {
+        self.map_positive(|w| w.ilog2() as i128)
+    }
+
+    /// Base 2 logarithm, rounded up.
+    pub fn clog2(self) -> Result<Val, Error> {
+        self.map_positive(|w| {
+            if w == 1 {
+                0
+            } else {
+                (w - 1).ilog2() as i128 + 1
+            }
+        })
+    }
+
+    /// Base 10 logarithm, rounded down.
+    pub fn log10(self) -> Result<Val, Error> {
+        self.map_positive(|w| w.ilog10() as i128)
+    }
+
+    /// Base 10 logarithm, rounded up.
+    pub fn clog10(self) -> Result<Val, Error> {
+        self.map_positive(|w| {
+            let log = w.ilog10();
+            if 10i128.pow(log) == w {
+                log as i128
+            } else {
+                log as i128 + 1
+            }
+        })
+    }
+
+    /// Smallest power of two greater than or equal to the value.
+    pub fn next_pow2(self) -> Result<Val, Error> {
+        match self.wide() {
+            w if w < 0 => Err(Error::Domain("power of two of a negative number")),
+            w => self.exact((w as u128).next_power_of_two() as i128),
         }
     }
-}
 
-impl ops::BitOr for Val {
-    type Output = Val;
-    fn bitor(self, rhs: Val) -> Val {
-        match self {
-            U64(u) => u | rhs,
-            I64(i) => i | rhs,
+    /// 1 if the value is a power of two, 0 otherwise.
+    pub fn is_pow2(&self) -> Val {
+        let w = self.wide();
+        U64((w > 0 && w & (w - 1) == 0) as u64)
+    }
+
+    fn map_positive(self, f: impl Fn(i128) -> i128) -> Result<Val, Error> {
+        match self.wide() {
+            0 => Err(Error::Domain("logarithm of zero")),
+            w if w < 0 => Err(Error::Domain("logarithm of a negative number")),
+            w => self.exact(f(w)),
         }
     }
+
+    /// Narrows the result of an op on this value alone, keeping its type.
+    /// Results that don't fit are an error in any mode.
+    fn exact(&self, wide: i128) -> Result<Val, Error> {
+        Val::narrow(wide, false, self.is_signed(), Mode::Checked)
+    }
 }
 
-impl ops::BitXor<Val> for u64 {
-    type Output = Val;
-    fn bitxor(self, rhs: Val) -> Val {
-        match rhs {
-            U64(u) => U64(self ^ u),
-            I64(i) => I64(self as i64 ^ i),
+impl Val {
+    /// Compares by numeric value, regardless of signedness.
+    pub fn compare(&self, other: &Val) -> Ordering {
+        self.wide().cmp(&other.wide())
+    }
+
+    pub fn min(self, other: Val) -> Val {
+        if other.compare(&self) == Ordering::Less {
+            other
+        } else {
+            self
         }
     }
-}
 
-impl ops::BitXor<Val> for i64 {
-    type Output = Val;
-    fn bitxor(self, rhs: Val) -> Val {
-        match rhs {
-            U64(u) => I64(self ^ u as i64),
-            I64(i) => I64(self ^ i),
+    pub fn max(self, other: Val) -> Val {
+        if other.compare(&self) == Ordering::Greater {
+            other
+        } else {
+            self
         }
     }
-}
 
-impl ops::BitXor for Val {
-    type Output = Val;
-    fn bitxor(self, rhs: Val) -> Val {
+    /// Negation. Negating a `U64` yields an `I64`.
+    pub fn neg(self, mode: Mode) -> Result<Val, Error> {
+        Val::narrow(-self.wide(), false, true, mode)
+    }
+
+    pub fn abs(self, mode: Mode) -> Result<Val, Error> {
+        Val::narrow(self.wide().abs(), false, self.is_signed(), mode)
+    }
+
+    /// -1, 0 or 1 according to the sign of the value.
+    pub fn sgn(self) -> Val {
+        Val::truncate(self.wide().signum(), self.is_signed())
+    }
+
+    /// Converts the value to an array index, which must not be negative.
+    pub fn to_index(&self) -> Result<u64, Error> {
+        u64::try_from(self.wide()).map_err(|_| Error::BadIndex)
+    }
+
+    /// Promotes the value to `i128`, which holds every `U64` and `I64`.
+    pub fn wide(&self) -> i128 {
         match self {
-            U64(u) => u ^ rhs,
-            I64(i) => i ^ rhs,
+            U64(u) => *u as i128,
+            I64(i) => *i as i128,
         }
     }
 }
 
-impl ops::Not for Val {
-    type Output = Val;
-    fn not(self) -> Val {
-        match self {
-            U64(u) => U64(!u),
-            I64(i) => I64(!i),
-        }
+/// The low 64 bits of an `i128`, the bit pattern of a 64 bit value.
+const LOW_BITS: i128 = u64::MAX as i128;
+
+/// Checks a shift amount. Negative amounts are always an error; amounts of 64
+/// or more are an error unless wrapping, where they saturate at 64.
+fn shift_amount(amount: Val, mode: Mode) -> Result<u32, Error> {
+    let n = u64::try_from(amount.wide()).map_err(|_| Error::BadShift)?;
+    match mode {
+        _ if n < 64 => Ok(n as u32),
+        Mode::Wrapping => Ok(64),
+        Mode::Checked => Err(Error::BadShift),
     }
 }
 
-impl ops::Shl<Val> for u64 {
-    type Output = Val;
-    fn shl(self, rhs: Val) -> Val {
-        match rhs {
-            U64(u) => U64(self << u),
-            I64(i) => I64((self as i64) << i),
+/// Raises `base` to `exp` by repeated squaring. The flag is set if the result
+/// overflowed an `i128`, in which case its low bits are still correct.
+fn pow_wide(mut base: i128, mut exp: u64) -> (i128, bool) {
+    let (mut acc, mut overflow) = (1i128, false);
+    while exp > 0 {
+        if exp & 1 == 1 {
+            let (r, o) = acc.overflowing_mul(base);
+            acc = r;
+            overflow |= o;
+        }
+        exp >>= 1;
+        // Only square when another bit needs it, so overflow is never spurious.
+        if exp > 0 {
+            let (r, o) = base.overflowing_mul(base);
+            base = r;
+            overflow |= o;
         }
     }
+    (acc, overflow)
 }
 
-impl ops::Shl<Val> for i64 {
-    type Output = Val;
-    fn shl(self, rhs: Val) -> Val {
-        match rhs {
-            U64(u) => I64(self << u as i64),
-            I64(i) => I64(self << i),
+/// Largest `r` such that `r.pow(n) <= w`, for `w >= 0` and `n >= 1`.
+fn iroot(w: i128, n: i128) -> i128 {
+    if n == 1 || w < 2 {
+        return w;
+    }
+    if n >= 127 {
+        return 1;
+    }
+    let fits = |r: i128| r.checked_pow(n as u32).is_some_and(|p| p <= w);
+    // The root of a 127 bit number is below 2^(127/n), so binary search up to
+    // that, rounded up. For n >= 2 the shift is at most 64.
+    let (mut lo, mut hi) = (1i128, 1i128 << (127 / n + 1));
+    while lo < hi {
+        let mid = lo + (hi - lo + 1) / 2;
+        if fits(mid) {
+            lo = mid;
+        } else {
+            hi = mid - 1;
         }
     }
+    lo
 }
-impl ops::Shl for Val {
-    type Output = Val;
-    fn shl(self, rhs: Val) -> Val {
-        match self {
-            U64(u) => u << rhs,
-            I64(i) => i << rhs,
-        }
+
+impl From<Val> for i32 {
+    fn from(v: Val) -> i32 {
+        v.wide() as i32
     }
 }
 
-impl ops::Shr<Val> for u64 {
-    type Output = Val;
-    fn shr(self, rhs: Val) -> Val {
-        match rhs {
-            U64(u) => U64(self >> u),
-            I64(i) => I64((self as i64) >> i),
-        }
+impl From<Val> for u32 {
+    fn from(v: Val) -> u32 {
+        v.wide() as u32
     }
 }
 
-impl ops::Shr<Val> for i64 {
-    type Output = Val;
-    fn shr(self, rhs: Val) -> Val {
-        match rhs {
-            U64(u) => I64(self >> u as i64),
-            I64(i) => I64(self >> i),
-        }
+impl From<Val> for u64 {
+    fn from(v: Val) -> u64 {
+        v.wide() as u64
     }
 }
 
-impl ops::Shr for Val {
-    type Output = Val;
-    fn shr(self, rhs: Val) -> Val {
-        match self {
-            U64(u) => u >> rhs,
-            I64(i) => i >> rhs,
-        }
+impl From<Val> for i64 {
+    fn from(v: Val) -> i64 {
+        v.wide() as i64
     }
 }
 
@@ -410,16 +478,16 @@ impl FromStr for Val {
                 return Ok(U64(u));
             }
         } else if let Some(s) = s.strip_prefix("-0b") {
-            if let Ok(i) = i64::from_str_radix(s, 2) {
-                return Ok(I64(-i));
+            if let Some(i) = negative(u64::from_str_radix(s, 2)) {
+                return Ok(I64(i));
             }
         } else if let Some(s) = s.strip_prefix("0x") {
             if let Ok(u) = u64::from_str_radix(s, 16) {
                 return Ok(U64(u));
             }
         } else if let Some(s) = s.strip_prefix("-0x") {
-            if let Ok(i) = i64::from_str_radix(s, 16) {
-                return Ok(I64(-i));
+            if let Some(i) = negative(u64::from_str_radix(s, 16)) {
+                return Ok(I64(i));
             }
         } else if let Ok(u) = s.parse::<u64>() {
             return Ok(U64(u));
@@ -429,3 +497,8 @@ impl FromStr for Val {
         Err(Error::InvalidInput(s.into()))
     }
 }
+
+/// Negates a parsed magnitude, which may be as large as `i64::MIN`'s.
+fn negative(magnitude: Result<u64, std::num::ParseIntError>) -> Option<i64> {
+    0i64.checked_sub_unsigned(magnitude.ok()?)
+}
@@ -0,0 +1,73 @@
+use crate::error::Error;
+use crate::stack::op::{Dialect, Op};
+use crate::stack::registers::RegId;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+/// A word defined with `: name body ;`.
+pub struct Word {
+    /// The body as written, for listing and saving.
+    pub src: String,
+    /// Each op with the token it was parsed from, for error reports.
+    pub ops: Rc<[(String, Op)]>,
+    /// How the body was read, so it reads the same when loaded again.
+    pub dialect: Dialect,
+}
+
+/// The dictionary of user-defined words. Words are looked up when they run,
+/// so a word can call itself or words defined after it.
+pub struct Words {
+    words: BTreeMap<String, Word>,
+}
+
+impl Words {
+    pub fn new() -> Self {
+        Words {
+            words: BTreeMap::new(),
+        }
+    }
+
+    /// Defines or redefines `name`, parsing its body in `dialect`. Names that
+    /// parse as a builtin op or a number can't be used, since they would
+    /// never be looked up. That includes a register op followed by any one
+    /// character, like `sq`, which names register `q`.
+    pub fn define(&mut self, name: &str, body: &[String], dialect: Dialect) -> Result<(), Error> {
+        match Op::parse(name, dialect) {
+            Ok(Op::Word(_)) if name != ":" && name != ";" => (),
+            Ok(Op::Reg(_, Some(RegId::Name(reg)))) => {
+                return Err(Error::BadDefinition(format!(
+                    "can't define '{}', which is a register op on register '{}'",
+                    name, reg
+                )))
+            }
+            _ => return Err(Error::BadDefinition(format!("can't define '{}'", name))),
+        }
+        let ops = body
+            .iter()
+            .map(|token| Ok((token.clone(), Op::parse(token, dialect)?)))
+            .collect::<Result<Vec<(String, Op)>, Error>>()?;
+        let word = Word {
+            src: body.join(" "),
+            ops: ops.into(),
+            dialect,
+        };
+        self.words.insert(name.into(), word);
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<Rc<[(String, Op)]>> {
+        self.words.get(name).map(|word| word.ops.clone())
+    }
+
+    pub fn forget(&mut self, name: &str) -> Result<(), Error> {
+        self.words
+            .remove(name)
+            .map(|_| ())
+            .ok_or_else(|| Error::UnknownWord(name.into()))
+    }
+
+    /// Every word, sorted by name.
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Word)> {
+        self.words.iter()
+    }
+}
@@ -0,0 +1,282 @@
+//! Runs dcrs as a program, for options that can't be tested on a `Stack`.
+
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Output, Stdio};
+
+/// Runs dcrs with `args` and `stdin`, without an rc file unless one is given.
+fn dcrs(args: &[&str], stdin: &str) -> Output {
+    let config = env::temp_dir().join("dcrs-cli-no-config");
+    dcrs_with(args, stdin, &config)
+}
+
+/// Runs dcrs with `config` as `$XDG_CONFIG_HOME`, for the default rc file.
+fn dcrs_with(args: &[&str], stdin: &str, config: &Path) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_dcrs"))
+        .args(args)
+        .env("XDG_CONFIG_HOME", config)
+        .env("XDG_DATA_HOME", env::temp_dir())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into()
+}
+
+#[test]
+fn map_hex_to_decimal() {
+    let output = dcrs(&["--map", "10 o"], "0x10 0xff\n0b101\n");
+    assert_eq!(stdout(&output), "16\n255\n5\n");
+    assert_eq!(stderr(&output), "");
+    assert!(output.status.success());
+}
+
+#[test]
+fn map_lines() {
+    let output = dcrs(&["--map", "sum", "--lines"], "1 2 3\n4 5\n");
+    assert_eq!(stdout(&output), "6\n9\n");
+}
+
+#[test]
+fn map_persist() {
+    let output = dcrs(&["--map", "+", "--persist"], "0\n1 2\n3\n");
+    assert_eq!(stdout(&output), "1\n3\n6\n");
+    assert_eq!(stderr(&output).lines().count(), 1);
+    let output = dcrs(&["--map", "z"], "5 6\n");
+    assert_eq!(stdout(&output), "1\n1\n");
+}
+
+#[test]
+fn map_input_is_not_run() {
+    let path = env::temp_dir().join("dcrs-cli-map-session");
+    let save = format!("save:{}", path.display());
+    let output = dcrs(&["--map", "1 +"], &format!("1 {} q 2\nnope\n", save));
+    assert_eq!(stdout(&output), "2\n3\n");
+    assert_eq!(
+        stderr(&output),
+        format!(
+            "<stdin>:1:3: Error: Invalid input: '{save}'\n\
+             <stdin>:1:{}: Error: Invalid input: 'q'\n\
+             <stdin>:2:1: Error: Invalid input: 'nope'\n",
+            4 + save.len()
+        )
+    );
+    assert!(!path.exists());
+    assert!(output.status.success());
+
+    let output = dcrs(&["--map", "sum", "--lines"], "1 x 2\n3\n");
+    assert_eq!(stdout(&output), "3\n");
+    assert_eq!(stderr(&output), "<stdin>:1:3: Error: Invalid input: 'x'\n");
+}
+
+#[test]
+fn map_empty_stack() {
+    let output = dcrs(&["--map", "drop"], "5\n");
+    assert_eq!(stdout(&output), "");
+    assert_eq!(
+        stderr(&output),
+        "<stdin>:1: Error: Stack has 0 item(s) but 1 are needed after --map\n"
+    );
+}
+
+#[test]
+fn map_errors_give_the_input_line() {
+    let output = dcrs(&["--map", "2 swap /"], "4\n0\n");
+    assert_eq!(stdout(&output), "0\n");
+    assert_eq!(
+        stderr(&output),
+        "<stdin>:2: Error: Attempt to divide by zero ('/' on 2 0) in --map:8\n"
+    );
+
+    // Blank lines aren't input, with or without --lines.
+    let output = dcrs(&["--map", "sum", "--lines"], "1 2\n\n  \n3\n");
+    assert_eq!(stdout(&output), "3\n3\n");
+    assert_eq!(stderr(&output), "");
+}
+
+#[test]
+fn caret_after_tabs() {
+    let output = dcrs(&[], "1\t0 /\n");
+    assert_eq!(
+        stderr(&output),
+        "   \t  ^\nError: Attempt to divide by zero ('/' on 1 0)\n"
+    );
+}
+
+#[test]
+fn deep_words_fail_cleanly() {
+    let output = dcrs(&["--max-depth", "1000000000"], "");
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).starts_with("--max-depth can be at most 50000\n"));
+
+    let script = script("dcrs-cli-rec.dc", ": rec rec ; rec\n");
+    let output = dcrs(&["--max-depth", "50000", "-f", &script], "");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stderr(&output),
+        format!(
+            "{}:1:13: Error: Words nested more than 50000 deep\n",
+            script
+        )
+    );
+}
+
+/// Writes `text` to a script in the temp dir and returns its path.
+fn script(name: &str, text: &str) -> String {
+    let path = env::temp_dir().join(name);
+    fs::write(&path, text).unwrap();
+    path.to_str().unwrap().into()
+}
+
+#[test]
+fn on_error_policies() {
+    let path = script("dcrs-cli-on-error.dc", "5 0 / f\n7 p\n");
+    let error = format!(
+        "{}:1:5: Error: Attempt to divide by zero ('/' on 5 0)\n",
+        path
+    );
+    let run = |policy: &[&str]| {
+        let mut args = policy.to_vec();
+        args.extend(["-f", &path]);
+        dcrs(&args, "")
+    };
+
+    let output = run(&["--on-error", "continue"]);
+    assert_eq!(stdout(&output), "5\n0\n7\n");
+    assert_eq!(stderr(&output), error);
+    assert!(output.status.success());
+
+    let output = run(&["--on-error", "line"]);
+    assert_eq!(stdout(&output), "7\n");
+    assert_eq!(stderr(&output), error);
+    assert!(output.status.success());
+
+    for policy in [&["--on-error", "abort"][..], &[]] {
+        let output = run(policy);
+        assert_eq!(stdout(&output), "");
+        assert_eq!(stderr(&output), error);
+        assert_eq!(output.status.code(), Some(1));
+    }
+}
+
+#[test]
+fn unterminated_definitions() {
+    let path = script("dcrs-cli-unterminated.dc", "1 p\n: www\n2\n");
+    let output = dcrs(&["-f", &path], "");
+    assert_eq!(stdout(&output), "1\n");
+    assert_eq!(
+        stderr(&output),
+        format!(
+            "{}:2: Error: Bad definition: unterminated definition of 'www'\n",
+            path
+        )
+    );
+    assert_eq!(output.status.code(), Some(1));
+
+    // The rc file carries on, and the REPL doesn't add to the definition.
+    let rc = script("dcrs-cli-unterminated-rc", ": sqr d *\n");
+    let output = dcrs(&["--rc", &rc], "3 4 + p\n5 p\n");
+    assert_eq!(stdout(&output), "► 7\n► 5\n► ");
+    assert_eq!(
+        stderr(&output),
+        format!(
+            "{}:1: Error: Bad definition: unterminated definition of 'sqr'\n",
+            rc
+        )
+    );
+    assert!(output.status.success());
+}
+
+#[test]
+fn rc_file() {
+    let rc = script("dcrs-cli-rc", ": square d * ;\n42 sa\n");
+    let output = dcrs(&["--rc", &rc], "3 square p la p\n");
+    assert_eq!(stdout(&output), "► 9\n42\n► ");
+    assert_eq!(stderr(&output), "");
+
+    // Without --rc, the rc file is found in the config directory.
+    let config = env::temp_dir().join("dcrs-cli-config");
+    fs::create_dir_all(config.join("dcrs")).unwrap();
+    fs::write(config.join("dcrs").join("dcrsrc"), ": square d * ;\n").unwrap();
+    let output = dcrs_with(
+        &["-f", &script("dcrs-cli-sq.dc", "4 square p\n")],
+        "",
+        &config,
+    );
+    assert_eq!(stdout(&output), "16\n");
+    let output = dcrs_with(
+        &["--no-rc", "-f", &script("dcrs-cli-sq.dc", "4 square p\n")],
+        "",
+        &config,
+    );
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn rc_errors_dont_stop_startup() {
+    let rc = script("dcrs-cli-bad-rc", "1 +\n: ok 7 ;\nnope ok\n");
+    let output = dcrs(&["--rc", &rc, "-f", &script("dcrs-cli-ok.dc", "f\n")], "");
+    assert_eq!(stdout(&output), "1\n7\n");
+    assert_eq!(
+        stderr(&output),
+        format!(
+            "{rc}:1:3: Error: Stack has 1 item(s) but 2 are needed\n\
+             {rc}:3:1: Error: 'nope' is not a number, an op or a word in the dictionary\n"
+        )
+    );
+    assert!(output.status.success());
+}
+
+#[test]
+fn rc_file_with_autosave() {
+    let session = env::temp_dir().join("dcrs-cli-autosave");
+    let _ = fs::remove_file(&session);
+    let session = session.to_str().unwrap();
+    let rc = script("dcrs-cli-autosave-rc", ": square d * ;\nhex\n");
+    let args = ["--rc", &rc, "--session", session, "--autosave"];
+    let output = dcrs(&args, "5 sb\n");
+    assert_eq!(stderr(&output), "");
+
+    // The saved session is loaded first, so the rc file's word and radix,
+    // and the register saved from the last run, are all there.
+    let output = dcrs(&args, "lb square p\n");
+    assert_eq!(stdout(&output), "► 0x19\n► ");
+    fs::remove_file(session).unwrap();
+}
+
+#[test]
+fn rc_values_with_autosave() {
+    let session = env::temp_dir().join("dcrs-cli-autosave-values");
+    let _ = fs::remove_file(&session);
+    let session = session.to_str().unwrap();
+    let rc = script("dcrs-cli-autosave-values-rc", "Ca 42 sa\n: one 1 ;\n");
+    let args = ["--rc", &rc, "--session", session, "--autosave"];
+    for _ in 0..2 {
+        let output = dcrs(&args, "");
+        assert_eq!(stderr(&output), "");
+    }
+
+    // The rc file runs after the session is loaded, so an edited value takes
+    // effect, as do its words.
+    script("dcrs-cli-autosave-values-rc", "Ca 43 sa\n: one 1 ;\n");
+    let output = dcrs(&args, "regs one p\n");
+    assert_eq!(stdout(&output), "► a            1  43\n1\n► ");
+    fs::remove_file(session).unwrap();
+}
@@ -0,0 +1,55 @@
+//! Runs the dc programs in `tests/dc` and checks their output. See
+//! `tests/dc/README.md` for the format and the deviations from GNU dc.
+
+use std::env;
+use std::fs::{self, File};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+#[test]
+fn dc_programs() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("dc");
+    let mut programs: Vec<_> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "dc"))
+        .collect();
+    programs.sort();
+    assert!(!programs.is_empty());
+
+    let mut failures = Vec::new();
+    for path in programs.iter() {
+        let name = path.file_name().unwrap();
+        let expected = |ext| fs::read_to_string(path.with_extension(ext)).unwrap_or_default();
+        let stdin = match File::open(path.with_extension("in")) {
+            Ok(file) => Stdio::from(file),
+            Err(_) => Stdio::null(),
+        };
+        let mut command = Command::new(env!("CARGO_BIN_EXE_dcrs"));
+        command.args(["--no-rc", "--on-error", "continue"]);
+        if name.to_string_lossy().starts_with("compat_") {
+            command.arg("--dc-compat");
+        }
+        let output = command
+            .arg("-f")
+            .arg(name)
+            .current_dir(&dir)
+            .env("XDG_DATA_HOME", env::temp_dir())
+            .stdin(stdin)
+            .output()
+            .unwrap();
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if stdout != expected("out") || stderr != expected("err") {
+            failures.push(format!(
+                "{}:\n--- stdout\n{}--- stderr\n{}",
+                name.to_string_lossy(),
+                stdout,
+                stderr
+            ));
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}